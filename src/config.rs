@@ -10,13 +10,41 @@ pub const MM_PER_INCH: f32 = 25.4;
 pub const PT_PER_INCH: f32 = 72.0;
 //pub const PT_TO_MM: f32 = MM_PER_INCH / PT_PER_INCH;
 pub const MM_TO_PT: f32 = PT_PER_INCH / MM_PER_INCH;
-pub const FONT_OFFSET_SCALE: f32 = 1.2;
-pub const PAGIN_SCALE: f32 = 0.5; //页码字体与标题字体大小比值
-pub const PUN_SCALE: f32 = 0.5; //非占位标点与正文字体大小比值
-pub const PUN_PUB: f32 = 0.8;   //占位符号Y偏移比例
+pub const FONT_OFFSET_SCALE: f32 = 1.2; //字距与字体大小比值（默认值）
+pub const PAGIN_SCALE: f32 = 0.5; //页码字体与标题字体大小比值（默认值）
+pub const PUN_SCALE: f32 = 0.5; //非占位标点与正文字体大小比值（默认值）
+pub const PUN_PUB: f32 = 0.8;   //占位符号Y偏移比例（默认值）
+pub const PUN_CENTER_X: f32 = 0.25; //非占位标点中心在前字右缘外的距离与标点大小比值
+pub const PUN_CENTER_Y: f32 = 0.1;  //非占位标点中心在前字基线上的高度与标点大小比值
+pub const RUBY_SCALE: f32 = 0.35;   //注音与正文字体大小比值
+pub const RUBY_GAP: f32 = 0.05;     //注音与正文字身的间距与正文字体大小比值
+pub const SECTION_INDENT: i32 = 1;  //节标题低格数
+pub const QUOTE_INDENT: i32 = 2;    //引文低格数
+pub const NOTE_INDENT: i32 = 3;     //注文低格数
+pub const NOTE_SCALE: f32 = 0.75;   //注文（小字）与正文字体大小比值
+pub const MAX_RAISE: i32 = 3;       //抬头格数上限
 pub const LINE_WIDTH_PT: f32 = 4.0; //粗线线宽
 pub const LINE_OFFSET_PT: f32 = 4.5; // 粗线与细线间距
 pub const LINE_SPACE_MM: f32 = 0.5; // 粗线与细线间距
+pub const THIN_LINE_WIDTH_PT: f32 = 0.5; //细线线宽
+pub const APP_NAME: &str = "古籍电子书制作"; //PDF 元数据中的制作程序名
+pub const BLEED_MM: f32 = 3.0;             //线装印刷：出血（默认值）
+pub const PRINT_MARK_GAP_MM: f32 = 2.0;    //线装印刷：裁切线与出血边的间距
+pub const PRINT_MARK_LENGTH_MM: f32 = 5.0; //线装印刷：裁切线、折页线长度
+pub const PRINT_MARK_WIDTH_PT: f32 = 0.25; //线装印刷：印刷标记线宽
+pub const PAPER_PRESETS_PATH: &str = "./presets.json"; //纸张预设文件
+pub const PAPER_CUSTOM: &str = "自定义"; //非预设尺寸的名称
+pub const CONVERT_TABLE_PATH: &str = "./convert.txt"; //简繁转换用户对照表
+pub const JITTER_OFFSET_SCALE: f32 = 0.04; //刻本效果：字符位移上限与字体大小比值
+pub const JITTER_ROTATE_DEG: f32 = 2.0;    //刻本效果：字符旋转上限（度）
+pub const JITTER_SIZE_SCALE: f32 = 0.04;   //刻本效果：字符大小变化上限比例
+pub const JITTER_INK: f32 = 0.25;          //刻本效果：墨色浓淡变化上限
+pub const LINE_WAVE_PT: f32 = 0.6;         //刻本效果：框线波动上限（点）
+pub const LINE_WAVE_STEP_PT: f32 = 12.0;   //刻本效果：框线波动采样间距（点）
+pub const LINE_BREAK_RATE: f32 = 0.03;     //刻本效果：框线断裂概率（每段）
+pub const IMAGE_DPI_MIN: u32 = 72;         //导出图片：分辨率下限
+pub const IMAGE_DPI_MAX: u32 = 1200;       //导出图片：分辨率上限
+pub const PNG_MEMORY_MB: f32 = 512.0;      //导出图片：同时渲染的整页位图内存上限（MB）
 /* 
 tag_comment=【】 #标识批注文字
 tag_newpage=% #分页符号
@@ -44,6 +72,18 @@ pub struct Pager {
     pub line_offset_pt: f32,
     pub title_font_size_pt: f32,
     pub content_font_size_pt: f32,
+    #[serde(default = "default_layout_mode")]
+    pub layout_mode: String,          // 排版方式：按字号、按行款
+    #[serde(default = "default_chars_per_column")]
+    pub chars_per_column: usize,      // 按行款排版时每列字数
+}
+
+fn default_layout_mode() -> String {
+    "按字号".to_string()
+}
+
+fn default_chars_per_column() -> usize {
+    20
 }
 
 
@@ -54,6 +94,185 @@ pub struct DrawColor {
     pub draw: String,
 }
 
+// 排版参数，随字体不同按书设置
+#[derive(Debug, Clone,Deserialize ,Serialize)]
+#[serde(default)]
+pub struct Typeset {
+    pub font_offset_scale: f32,     // 字距与字体大小比值
+    pub pun_scale: f32,             // 非占位标点与正文字体大小比值
+    pub pun_pub: f32,               // 占位符号Y偏移比例
+    pub pagin_scale: f32,           // 页码字体与标题字体大小比值
+    pub punctuation_mode: String,   // 标点处理：保留标点、白文、句读
+    pub kinsoku_mode: String,       // 避头尾：悬挂、挤入
+    pub script_convert: String,     // 简繁转换：不转换、简转繁、繁转简
+    pub number_mode: String,        // 数字转换：按上下文、逐字、保留
+    pub convert_table_path: String, // 简繁转换用户对照表路径
+    pub substitutions: Vec<Substitution>, // 字符替换表，按最长匹配替换
+}
+
+// 替换规则：单字到单字，或字符串到字符串
+#[derive(Debug, Clone,Deserialize ,Serialize)]
+pub struct Substitution {
+    pub from: String,
+    pub to: String,
+}
+
+impl Default for Typeset {
+    fn default() -> Self {
+        Typeset {
+            font_offset_scale: FONT_OFFSET_SCALE,
+            pun_scale: PUN_SCALE,
+            pun_pub: PUN_PUB,
+            pagin_scale: PAGIN_SCALE,
+            punctuation_mode: "保留标点".to_string(),
+            kinsoku_mode: "悬挂".to_string(),
+            script_convert: "不转换".to_string(),
+            number_mode: "按上下文".to_string(),
+            convert_table_path: CONVERT_TABLE_PATH.to_string(),
+            substitutions: default_substitutions(),
+        }
+    }
+}
+
+impl Typeset {
+    /// 字距、标点、页码比值须为正数，手工改过的配置文件中非正数的比值回退为默认值
+    pub fn validated(mut self) -> Self {
+        self.font_offset_scale = positive_scale(self.font_offset_scale, FONT_OFFSET_SCALE);
+        self.pun_scale = positive_scale(self.pun_scale, PUN_SCALE);
+        self.pagin_scale = positive_scale(self.pagin_scale, PAGIN_SCALE);
+        self
+    }
+}
+
+// 比值为零或负数时字距、每列字数无意义，取默认值
+pub fn positive_scale(value: f32, default: f32) -> f32 {
+    if value.is_finite() && value > 0.0 { value } else { default }
+}
+
+// 默认替换表：@ 及控制符转空格、弯引号转直排引号，数字由数字转换处理
+fn default_substitutions() -> Vec<Substitution> {
+    [
+        ("@", " "), ("\r", " "), ("\t", " "),
+        ("“", "『"), ("”", "』"), ("‘", "「"), ("’", "」"),
+    ]
+    .iter()
+    .map(|(from, to)| Substitution { from: from.to_string(), to: to.to_string() })
+    .collect()
+}
+
+// 替换表转为界面文本：每行“原文=替换”，\t \r \n \\ \= 为转义
+pub fn substitutions_to_text(substitutions: &[Substitution]) -> String {
+    let escape = |text: &str| -> String {
+        text.chars()
+            .map(|c| match c {
+                '\\' => "\\\\".to_string(),
+                '\t' => "\\t".to_string(),
+                '\r' => "\\r".to_string(),
+                '\n' => "\\n".to_string(),
+                '=' => "\\=".to_string(),
+                _ => c.to_string(),
+            })
+            .collect()
+    };
+    substitutions
+        .iter()
+        .map(|sub| format!("{}={}", escape(&sub.from), escape(&sub.to)))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// 由界面文本解析替换表，以第一个未转义的“=”分隔原文和替换，忽略空行
+pub fn substitutions_from_text(text: &str) -> Vec<Substitution> {
+    let mut substitutions = Vec::new();
+    for line in text.lines() {
+        let mut from = String::new();
+        let mut to = String::new();
+        let mut in_to = false;
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            let c = match c {
+                '\\' => match chars.next() {
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('n') => '\n',
+                    Some(other) => other,
+                    None => '\\',
+                },
+                '=' if !in_to => {
+                    in_to = true;
+                    continue;
+                }
+                _ => c,
+            };
+            if in_to { to.push(c) } else { from.push(c) }
+        }
+        if in_to && !from.is_empty() {
+            substitutions.push(Substitution { from, to });
+        }
+    }
+    substitutions
+}
+
+#[derive(Debug, Clone,Deserialize ,Serialize)]
+#[serde(default)]
+pub struct Style {
+    pub woodblock: bool,            // 刻本效果
+    pub woodblock_seed: u64,        // 刻本效果随机种子
+    pub woodblock_strength: f32,    // 刻本效果强度（0~1）
+    pub frame_style: String,        // 版框：四周双边、四周单边、左右双边、无边框
+    pub thin_line_width_pt: f32,    // 细线线宽（点）
+    pub tail_count: String,         // 鱼尾数量：双鱼尾、单鱼尾
+    pub tail_fill: String,          // 鱼尾样式：黑鱼尾、白鱼尾、花鱼尾
+    pub tail_direction: String,     // 鱼尾方向：对鱼尾、顺鱼尾
+    pub xiangbi: String,            // 象鼻：黑口、线黑口、白口
+    pub xiangbi_width_pt: f32,      // 黑口线宽（点）
+    pub column_rules: bool,         // 界栏（列线），关闭为无界
+    pub ear: bool,                  // 书耳
+    pub ear_position: String,       // 书耳位置：左上、右上
+    pub ear_width_mm: f32,          // 书耳宽度（毫米）
+    pub ear_height_mm: f32,         // 书耳高度（毫米）
+    pub ear_source: String,         // 书耳文字：书名、章名、自定义
+    pub ear_text: String,           // 自定义书耳文字
+    pub print_mode: String,         // 印刷模式：屏幕阅读、线装印刷
+    pub bleed_mm: f32,              // 出血（毫米），线装印刷时有效
+    pub imposition: String,         // 拼版：不拼版、骑马订、二拼、四拼，纵向单页时有效
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            woodblock: false,
+            woodblock_seed: 1,
+            woodblock_strength: 0.5,
+            frame_style: "四周双边".to_string(),
+            thin_line_width_pt: THIN_LINE_WIDTH_PT,
+            tail_count: "双鱼尾".to_string(),
+            tail_fill: "黑鱼尾".to_string(),
+            tail_direction: "对鱼尾".to_string(),
+            xiangbi: "黑口".to_string(),
+            xiangbi_width_pt: LINE_WIDTH_PT,
+            column_rules: true,
+            ear: false,
+            ear_position: "左上".to_string(),
+            ear_width_mm: 5.0,
+            ear_height_mm: 24.0,
+            ear_source: "章名".to_string(),
+            ear_text: String::new(),
+            print_mode: "屏幕阅读".to_string(),
+            bleed_mm: BLEED_MM,
+            imposition: "不拼版".to_string(),
+        }
+    }
+}
+
+// 纸张预设，宽高按纵向（宽 < 高）记录
+#[derive(Debug, Clone,Deserialize ,Serialize)]
+pub struct PaperPreset {
+    pub name: String,
+    pub width_mm: f32,
+    pub height_mm: f32,
+}
+
 #[derive(Debug, Clone,Deserialize ,Serialize)]
 pub struct Canvas {
     pub point_left_bottom: Point,
@@ -71,6 +290,12 @@ pub struct Canvas {
     pub column_width_pt: Pt,
     pub line_width_pt: Pt,
     pub line_offset_pt: Pt,
+    pub thin_line_width_pt: Pt,
+
+    pub frame_visible: bool,          // 是否绘制版框
+    pub frame_offset_x_pt: Pt,        // 粗框左右外扩距离，0为单边
+    pub frame_offset_y_pt: Pt,        // 粗框上下外扩距离，0为单边
+    pub column_rules: bool,           // 是否绘制列线
 }
 
 #[derive(Debug, Clone,Deserialize ,Serialize)]
@@ -89,6 +314,11 @@ pub struct Tail {
     pub point_line_up_right: Point,
     pub point_line_down_left: Point, 
     pub point_line_down_right: Point, 
+
+    pub double: bool,                 // 是否绘制下鱼尾
+    pub fill_style: String,           // 黑鱼尾、白鱼尾、花鱼尾
+    pub down_flipped: bool,           // 顺鱼尾：下鱼尾与上鱼尾同向
+    pub xiangbi_width_pt: Pt,         // 象鼻线宽，0为白口
 }
 
 #[derive(Debug, Clone,Deserialize ,Serialize)]
@@ -98,9 +328,33 @@ pub struct FileInfo {
     pub name: String,
     pub author: String,
     pub creater: String,
+    #[serde(default)]
+    pub dynasty: String,              // 朝代
+    #[serde(default)]
+    pub editor: String,               // 整理者（校点、编辑）
+    #[serde(default)]
+    pub subject: String,              // 主题（内容简介）
+    #[serde(default)]
+    pub keywords: String,             // 关键词，以逗号、顿号或空格分隔
+    #[serde(default)]
+    pub edition: String,              // 版本，如“清光绪刻本”
+    #[serde(default)]
+    pub source: String,               // 底本来源
     pub main_path: String,
     pub backup_path: String,
     pub compressratio:u8,
+    #[serde(default = "default_image_dpi")]
+    pub image_dpi: u32,               // 导出图片的分辨率
+    #[serde(default = "default_svg_text")]
+    pub svg_text: String,             // SVG文字、SVG轮廓
+}
+
+fn default_image_dpi() -> u32 {
+    300
+}
+
+fn default_svg_text() -> String {
+    "SVG文字".to_string()
 }
 
 #[derive(Debug, Clone,Deserialize ,Serialize)]
@@ -119,7 +373,20 @@ pub struct Pagination {
     pub loc_start_x_pt: Pt,            // 分页开始x坐标
     pub loc_start_y_pt: Pt,            // 分页开始y坐标
     pub font_size_pt: f32,              // 字体大小
+    pub space_y_pt: Pt,                // 纵向间距
 }
+#[derive(Debug, Clone,Deserialize ,Serialize)]
+pub struct Ear {
+    pub visible: bool,                 // 是否绘制书耳
+    pub point_left_bottom: Point,      // 书耳框左下角
+    pub point_right_top: Point,        // 书耳框右上角
+    pub loc_start_x_pt: Pt,            // 文字开始x坐标
+    pub loc_start_y_pt: Pt,            // 文字开始y坐标
+    pub space_y_pt: Pt,                // 纵向间距
+    pub max_chars: usize,              // 最大字符数
+    pub font_size_pt: f32,             // 字体大小
+}
+
 #[derive(Debug, Clone,Deserialize ,Serialize)]
 pub struct Content {
 
@@ -131,9 +398,11 @@ pub struct Content {
     pub max_chars: i32,                 // 最大字符数
     pub font_size_pt: f32,              // 字体大小
     pub pun_font_size_pt: f32,          // 标点大小
+    pub pun_offset_y_pt: Pt,           // 占位符号Y偏移
+    pub hanging: bool,                 // 避头符号悬挂（否则挤入）
 }
 
-pub fn get_content_loc(page: &Pager) -> Content {
+pub fn get_content_loc(page: &Pager,typeset: &Typeset) -> Content {
 
     let page_width_pt = Pt(page.page_width_mm * MM_TO_PT);
     let page_height_pt = Pt(page.page_height_mm * MM_TO_PT);
@@ -148,9 +417,25 @@ pub fn get_content_loc(page: &Pager) -> Content {
     let height_pt = page_height_pt - page_top_margin_pt - page_bottom_margin_pt;
     let column_width_pt =  (width_pt - center_width_pt) / column_count as f32;
 
-    let content_font_size_pt = page.content_font_size_pt;
+    // 按行款排版：每列字数固定，字距均分版框高度，字号取字距与列宽的较小者
+    let by_chars = page.layout_mode == "按行款" && page.chars_per_column > 0;
+    let (content_font_size_pt, space_y_pt, max_chars) = if by_chars {
+        let space = height_pt.0 / page.chars_per_column as f32;
+        let size = (space / typeset.font_offset_scale).min(column_width_pt.0 * 0.8);
+        (size, space, page.chars_per_column as i32)
+    } else {
+        let size = page.content_font_size_pt;
+        (size, size * typeset.font_offset_scale, (height_pt.0 / (size * typeset.font_offset_scale)) as i32)
+    };
     let content_size = Pt(content_font_size_pt); 
+    // 字身框在列内居中，绘制时再按各字形外框微调到列中线
     let content_offset = (column_width_pt - content_size) / 2.0;
+    // 按行款时字符在格内垂直居中，按字号时沿用顶端对齐
+    let loc_y_pt = if by_chars {
+        page_height_pt - page_top_margin_pt - Pt((space_y_pt + content_font_size_pt) / 2.0)
+    } else {
+        page_height_pt - page_top_margin_pt - content_size
+    };
     let loc_x_pt: Vec<Pt> = if width_pt > height_pt {
         (0..column_count)
         .map(|i| {
@@ -169,18 +454,52 @@ pub fn get_content_loc(page: &Pager) -> Content {
     let content = Content{
         content_offset : content_offset,
         loc_x_pt: loc_x_pt,
-        loc_y_pt: page_height_pt - page_top_margin_pt - content_size,
+        loc_y_pt: loc_y_pt,
         space_x_pt: Pt(- column_width_pt.0),
-        space_y_pt: Pt(- content_size.0 * FONT_OFFSET_SCALE),
-        max_chars: (height_pt.0 / (content_font_size_pt * FONT_OFFSET_SCALE) )as i32,
+        space_y_pt: Pt(- space_y_pt),
+        max_chars: max_chars,
         font_size_pt: content_font_size_pt,
-        pun_font_size_pt: content_font_size_pt * PUN_SCALE,
+        pun_font_size_pt: content_font_size_pt * typeset.pun_scale,
+        pun_offset_y_pt: Pt(content_font_size_pt * typeset.pun_pub),
+        hanging: typeset.kinsoku_mode != "挤入",
     };
     content
 }
 
-pub fn get_pagination_loc(page: &Pager) -> Pagination {
-    let font_size_pt = page.title_font_size_pt * PAGIN_SCALE;
+// 书耳贴于版框外侧上角，位置以粗框外沿为准
+pub fn get_ear_loc(canvas: &Canvas, style: &Style, typeset: &Typeset) -> Ear {
+    let ear_width_pt = Pt(style.ear_width_mm * MM_TO_PT);
+    let ear_height_pt = Pt(style.ear_height_mm * MM_TO_PT);
+    let frame_half_pt = if canvas.frame_visible { canvas.line_width_pt / 2.0 } else { Pt(0.0) };
+    let top_pt = canvas.point_left_top.y + canvas.frame_offset_y_pt + frame_half_pt;
+    let left_pt = if style.ear_position == "右上" {
+        canvas.point_right_top.x + canvas.frame_offset_x_pt + frame_half_pt
+    } else {
+        canvas.point_left_top.x - canvas.frame_offset_x_pt - frame_half_pt - ear_width_pt
+    };
+    let font_size_pt = (ear_width_pt.0 * 0.7).min(ear_height_pt.0);
+    let space_y_pt = Pt(font_size_pt * typeset.font_offset_scale);
+    let ear = Ear {
+        visible: style.ear,
+        point_left_bottom: Point {
+            x: left_pt,
+            y: top_pt - ear_height_pt,
+        },
+        point_right_top: Point {
+            x: left_pt + ear_width_pt,
+            y: top_pt,
+        },
+        loc_start_x_pt: left_pt + (ear_width_pt - Pt(font_size_pt)) / 2.0,
+        loc_start_y_pt: top_pt - space_y_pt,
+        space_y_pt: space_y_pt,
+        max_chars: (ear_height_pt.0 / space_y_pt.0).floor().max(0.0) as usize,
+        font_size_pt: font_size_pt,
+    };
+    ear
+}
+
+pub fn get_pagination_loc(page: &Pager,typeset: &Typeset) -> Pagination {
+    let font_size_pt = page.title_font_size_pt * typeset.pagin_scale;
     let page_width_pt = Pt(page.page_width_mm * MM_TO_PT);
     let page_height_pt = Pt(page.page_height_mm * MM_TO_PT);
     let page_right_margin_pt = Pt(page.page_right_margin_mm * MM_TO_PT);
@@ -202,11 +521,12 @@ pub fn get_pagination_loc(page: &Pager) -> Pagination {
         loc_start_x_pt: loc_x_pt + offset_x_pt,
         loc_start_y_pt: loc_y_pt + offset_y_pt,
         font_size_pt: font_size_pt,     
+        space_y_pt: Pt(font_size_pt * typeset.font_offset_scale),
     };
     pagination
 }
 
-pub fn get_title_loc(page: &Pager) -> Title {
+pub fn get_title_loc(page: &Pager,typeset: &Typeset) -> Title {
     let page_width_pt = Pt(page.page_width_mm * MM_TO_PT);
     let page_height_pt = Pt(page.page_height_mm * MM_TO_PT);
     let page_right_margin_pt = Pt(page.page_right_margin_mm * MM_TO_PT);
@@ -229,17 +549,26 @@ pub fn get_title_loc(page: &Pager) -> Title {
     let title = Title{
         title_offset: title_offset,
         loc_start_x_pt: loc_x_pt + Pt(title_size.0 / 2.0),
-        loc_start_y_pt: loc_up_y_pt - Pt(title_size.0 * FONT_OFFSET_SCALE) * 2.0,
+        loc_start_y_pt: loc_up_y_pt - Pt(title_size.0 * typeset.font_offset_scale) * 2.0,
         space_x_pt: Pt(0.0),
-        space_y_pt: Pt(- title_size.0 * FONT_OFFSET_SCALE),
+        space_y_pt: Pt(- title_size.0 * typeset.font_offset_scale),
         max_chars: ((loc_up_y_pt.0 - loc_down_y_pt.0 
                   - center_width_pt.0)
-                / (title_size.0 * FONT_OFFSET_SCALE) ) as i32 - 1,
+                / (title_size.0 * typeset.font_offset_scale) ) as i32 - 1,
         font_size_pt: page.title_font_size_pt,       
     };
     title
 }
-pub fn get_tail_horizontal(page: &Pager) -> Tail {
+// 象鼻线宽：黑口取设定线宽，线黑口取细线宽，白口不绘制
+fn get_xiangbi_width(style: &Style) -> Pt {
+    match style.xiangbi.as_str() {
+        "白口" => Pt(0.0),
+        "线黑口" => Pt(style.thin_line_width_pt),
+        _ => Pt(style.xiangbi_width_pt),
+    }
+}
+
+pub fn get_tail_horizontal(page: &Pager,style: &Style) -> Tail {
     let page_width_pt = Pt(page.page_width_mm * MM_TO_PT);
     let page_height_pt = Pt(page.page_height_mm * MM_TO_PT);
     let page_top_margin_pt = Pt(page.page_top_margin_mm * MM_TO_PT);
@@ -248,9 +577,17 @@ pub fn get_tail_horizontal(page: &Pager) -> Tail {
     let tail_margin_pt = Pt(page.tail_margin_mm * MM_TO_PT);
     let tail_long_offset_pt = Pt(page.tail_long_offset_mm * MM_TO_PT);
     let tail_short_offset_pt = Pt(page.tail_short_offset_mm * MM_TO_PT);
-    let tail_space_pt = Pt(LINE_SPACE_MM * MM_TO_PT);
+    let tail_space_pt = Pt(page.tail_space_mm * MM_TO_PT);
+    let down_flipped = style.tail_direction == "顺鱼尾";
+    // 顺鱼尾时下鱼尾缺口朝下，与上鱼尾一致
+    let tail_down_notch_pt = if down_flipped { tail_long_offset_pt - tail_short_offset_pt } else { tail_short_offset_pt };
 
     let tail = Tail {        
+        double: style.tail_count != "单鱼尾",
+        fill_style: style.tail_fill.clone(),
+        down_flipped: down_flipped,
+        xiangbi_width_pt: get_xiangbi_width(style),
+
         point_up_left_bottom: Point{
             x: (page_width_pt - center_width_pt) / 2.0, 
             y: page_height_pt - page_top_margin_pt
@@ -294,7 +631,7 @@ pub fn get_tail_horizontal(page: &Pager) -> Tail {
         },        
         point_down_center: Point{
             x:(page_width_pt) / 2.0, 
-            y:(page_bottom_margin_pt  + tail_margin_pt + tail_short_offset_pt),
+            y:(page_bottom_margin_pt  + tail_margin_pt + tail_down_notch_pt),
         },
         
         point_line_up_left: Point{
@@ -318,7 +655,7 @@ pub fn get_tail_horizontal(page: &Pager) -> Tail {
     };
     tail
 }
-pub fn get_tail_vertical(page: &Pager) -> Tail {
+pub fn get_tail_vertical(page: &Pager,style: &Style) -> Tail {
     let page_width_pt = Pt(page.page_width_mm * MM_TO_PT);
     let page_height_pt = Pt(page.page_height_mm * MM_TO_PT);
     let page_right_margin_pt = Pt(page.page_right_margin_mm * MM_TO_PT);
@@ -328,9 +665,17 @@ pub fn get_tail_vertical(page: &Pager) -> Tail {
     let tail_margin_pt = Pt(page.tail_margin_mm * MM_TO_PT);
     let tail_long_offset_pt = Pt(page.tail_long_offset_mm * MM_TO_PT);
     let tail_short_offset_pt = Pt(page.tail_short_offset_mm * MM_TO_PT);
-    let tail_space_pt = Pt(LINE_SPACE_MM * MM_TO_PT);
+    let tail_space_pt = Pt(page.tail_space_mm * MM_TO_PT);
+    let down_flipped = style.tail_direction == "顺鱼尾";
+    // 顺鱼尾时下鱼尾缺口朝下，与上鱼尾一致
+    let tail_down_notch_pt = if down_flipped { tail_long_offset_pt - tail_short_offset_pt } else { tail_short_offset_pt };
 
     let tail = Tail {        
+        double: style.tail_count != "单鱼尾",
+        fill_style: style.tail_fill.clone(),
+        down_flipped: down_flipped,
+        xiangbi_width_pt: get_xiangbi_width(style),
+
         point_up_left_bottom: Point{
             x: page_width_pt - page_right_margin_pt -center_width_pt, 
             y: page_height_pt - page_top_margin_pt
@@ -373,7 +718,7 @@ pub fn get_tail_vertical(page: &Pager) -> Tail {
         },        
         point_down_center: Point{
             x: page_width_pt - page_right_margin_pt - center_width_pt / 2.0, 
-            y: page_bottom_margin_pt  + tail_margin_pt + tail_short_offset_pt,
+            y: page_bottom_margin_pt  + tail_margin_pt + tail_down_notch_pt,
         },
         
         point_line_up_left: Point{
@@ -398,7 +743,31 @@ pub fn get_tail_vertical(page: &Pager) -> Tail {
     tail
 }
 
-pub fn get_canvas_horizontal(page: &Pager) -> Canvas {
+// 推荐参数：鱼尾偏移按鱼尾留白比例、字体按中心宽与列宽推算
+pub fn suggest_pager(page: &Pager) -> Pager {
+    let col_width_mm = (page.page_width_mm - page.center_width_mm
+                        - page.page_left_margin_mm - page.page_right_margin_mm)
+                       / page.column_count.max(1) as f32;
+    Pager {
+        tail_long_offset_mm: page.tail_margin_mm * 0.4,
+        tail_short_offset_mm: page.tail_margin_mm * 0.25,
+        title_font_size_pt: (page.center_width_mm * 0.45 * MM_TO_PT).round(),
+        content_font_size_pt: (col_width_mm * 0.6 * MM_TO_PT).round(),
+        ..page.clone()
+    }
+}
+
+// 根据版框样式计算粗框外扩距离：(是否绘制, 左右外扩, 上下外扩)
+fn get_frame_offset(style: &Style, line_offset_pt: Pt) -> (bool, Pt, Pt) {
+    match style.frame_style.as_str() {
+        "四周单边" => (true, Pt(0.0), Pt(0.0)),
+        "左右双边" => (true, line_offset_pt, Pt(0.0)),
+        "无边框" => (false, Pt(0.0), Pt(0.0)),
+        _ => (true, line_offset_pt, line_offset_pt), // 默认四周双边
+    }
+}
+
+pub fn get_canvas_horizontal(page: &Pager,style: &Style) -> Canvas {
     let page_width_pt = Pt(page.page_width_mm * MM_TO_PT);
     let page_height_pt = Pt(page.page_height_mm * MM_TO_PT);
     let page_left_margin_pt = Pt(page.page_left_margin_mm * MM_TO_PT);
@@ -409,6 +778,7 @@ pub fn get_canvas_horizontal(page: &Pager) -> Canvas {
     let line_width_pt = Pt(page.line_width_pt);
     let line_offset_pt = Pt(page.line_offset_pt);
     let column_count = page.column_count;
+    let (frame_visible, frame_offset_x_pt, frame_offset_y_pt) = get_frame_offset(style, line_offset_pt);
 
     let width_pt = page_width_pt - page_left_margin_pt - page_right_margin_pt;
     let height_pt = page_height_pt - page_top_margin_pt - page_bottom_margin_pt;
@@ -417,6 +787,11 @@ pub fn get_canvas_horizontal(page: &Pager) -> Canvas {
     let canvas = Canvas {
         line_width_pt: line_width_pt,
         line_offset_pt: line_offset_pt,
+        thin_line_width_pt: Pt(style.thin_line_width_pt),
+        frame_visible: frame_visible,
+        frame_offset_x_pt: frame_offset_x_pt,
+        frame_offset_y_pt: frame_offset_y_pt,
+        column_rules: style.column_rules,
 
         width_pt: width_pt,
         height_pt: height_pt,
@@ -458,7 +833,7 @@ pub fn get_canvas_horizontal(page: &Pager) -> Canvas {
     canvas
 }
 
-pub fn get_canvas_vertical(page: &Pager) -> Canvas {
+pub fn get_canvas_vertical(page: &Pager,style: &Style) -> Canvas {
     let page_width_pt = Pt(page.page_width_mm * MM_TO_PT);
     let page_height_pt = Pt(page.page_height_mm * MM_TO_PT);
     let page_left_margin_pt = Pt(page.page_left_margin_mm * MM_TO_PT);
@@ -469,6 +844,7 @@ pub fn get_canvas_vertical(page: &Pager) -> Canvas {
     let line_width_pt = Pt(page.line_width_pt);
     let line_offset_pt = Pt(page.line_offset_pt);
     let column_count = page.column_count;
+    let (frame_visible, frame_offset_x_pt, frame_offset_y_pt) = get_frame_offset(style, line_offset_pt);
 
     let width_pt = page_width_pt - page_left_margin_pt - page_right_margin_pt;
     let height_pt = page_height_pt - page_top_margin_pt - page_bottom_margin_pt;
@@ -477,6 +853,11 @@ pub fn get_canvas_vertical(page: &Pager) -> Canvas {
     let canvas = Canvas {
         line_width_pt: line_width_pt,
         line_offset_pt: line_offset_pt,
+        thin_line_width_pt: Pt(style.thin_line_width_pt),
+        frame_visible: frame_visible,
+        frame_offset_x_pt: frame_offset_x_pt,
+        frame_offset_y_pt: frame_offset_y_pt,
+        column_rules: style.column_rules,
 
         width_pt: width_pt,
         height_pt: height_pt,
@@ -548,7 +929,7 @@ pub fn get_canvas_vertical(page: &Pager) -> Canvas {
     Ok(param)
 } */
 
-fn default() -> (Pager,DrawColor,FileInfo) {
+fn default() -> (Pager,DrawColor,FileInfo,Style,Typeset) {
     let page = Pager {
         page_width_mm: 297.0,                   // 页面宽度（毫米）
         page_height_mm: 210.0,                  // 页面高度（毫米）
@@ -566,6 +947,8 @@ fn default() -> (Pager,DrawColor,FileInfo) {
         line_width_pt: LINE_WIDTH_PT,           // 粗线框偏移（点）
         content_font_size_pt: 18.0,             // 内容字体大小（点）
         title_font_size_pt: 24.0,             // 标题字体大小（点）
+        layout_mode: default_layout_mode(),     // 排版方式
+        chars_per_column: default_chars_per_column(), // 每列字数
     };              
 
     let drawcolor = DrawColor {
@@ -578,13 +961,21 @@ fn default() -> (Pager,DrawColor,FileInfo) {
         main_path: "./fonts/qiji-combo.ttf".to_string(),      // 主字体路径
         backup_path: "./fonts/simsun.ttc".to_string(),        // 备用字体路径
         name: "庄子".to_string(),                           // 书籍名称
-        author: "庄子".to_string(),                         // 书籍作者
+        author: "庄周".to_string(),                         // 书籍作者
         creater: "测试创建人".to_string(),                    // PDF创建人
+        dynasty: "战国".to_string(),                        // 朝代
+        editor: String::new(),                              // 整理者
+        subject: String::new(),                             // 主题
+        keywords: String::new(),                            // 关键词
+        edition: String::new(),                             // 版本
+        source: String::new(),                              // 底本来源
         inputpath: "./text/001.txt".to_string(),           // 书籍输入路径
         outputpath: "./pdf/庄子.pdf".to_string(),           // 书籍输出路径 
         compressratio: 50,
+        image_dpi: default_image_dpi(),                     // 导出图片分辨率
+        svg_text: default_svg_text(),                       // SVG 文字保留为文本或转为轮廓
     };
-    (page,drawcolor,fileinfo)
+    (page,drawcolor,fileinfo,Style::default(),Typeset::default())
 }
 
 // 定义一个“根结构体”，组合多个数据结构
@@ -593,13 +984,19 @@ struct AppData {
     page: Pager,       // 嵌套集合
     drawcolor: DrawColor,  // 单个结构体
     fileinfo: FileInfo,  // 可选的键值对
+    #[serde(default)]
+    style: Style,        // 版式风格，旧配置缺省时取默认值
+    #[serde(default)]
+    typeset: Typeset,    // 排版参数，旧配置缺省时取默认值
 }
-pub fn save_json(page: Pager,drawcolor:DrawColor,fileinfo:FileInfo,json_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub fn save_json(page: Pager,drawcolor:DrawColor,fileinfo:FileInfo,style:Style,typeset:Typeset,json_path: &str) -> Result<(), Box<dyn std::error::Error>> {
     // 组合成根结构体
     let app_data = AppData {
         page: page,
         drawcolor: drawcolor,
         fileinfo: fileinfo,
+        style: style,
+        typeset: typeset,
     };
     let json_str = to_string_pretty(&app_data)?;    
     // 写入文件
@@ -608,7 +1005,7 @@ pub fn save_json(page: Pager,drawcolor:DrawColor,fileinfo:FileInfo,json_path: &s
     Ok(())
 }
 // 从JSON文件读取并解析为Base实例
-pub fn from_json_file(config_path: &str) -> (Pager,DrawColor,FileInfo) {
+pub fn from_json_file(config_path: &str) -> (Pager,DrawColor,FileInfo,Style,Typeset) {
     // 尝试读取文件
     let json_content = match fs::read_to_string(Path::new(config_path)) {
         Ok(content) => content,
@@ -619,13 +1016,53 @@ pub fn from_json_file(config_path: &str) -> (Pager,DrawColor,FileInfo) {
     };
     // 尝试解析JSON
     match from_str::<AppData>(&json_content){
-        Ok(app_data) => (app_data.page, app_data.drawcolor, app_data.fileinfo),
+        Ok(app_data) => (app_data.page, app_data.drawcolor, app_data.fileinfo, app_data.style, app_data.typeset.validated()),
         Err(e) => {
             eprintln!("⚠️ JSON解析失败：{}，将使用默认配置", e);
             default() // 返回默认值
         }
     }    
 }
+// 内置纸张预设，预设文件缺失或解析失败时使用
+fn default_paper_presets() -> Vec<PaperPreset> {
+    [("A4", 210.0, 297.0), ("B5", 176.0, 250.0), ("16K", 184.0, 260.0), ("32K", 130.0, 184.0)]
+        .iter()
+        .map(|&(name, width_mm, height_mm)| PaperPreset { name: name.to_string(), width_mm, height_mm })
+        .collect()
+}
+
+// 从JSON文件读取纸张预设
+pub fn load_paper_presets(presets_path: &str) -> Vec<PaperPreset> {
+    let json_content = match fs::read_to_string(Path::new(presets_path)) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("⚠️ 纸张预设读取失败：{}，将使用内置预设", e);
+            return default_paper_presets();
+        }
+    };
+    match from_str::<Vec<PaperPreset>>(&json_content) {
+        Ok(presets) if !presets.is_empty() => presets,
+        Ok(_) => default_paper_presets(),
+        Err(e) => {
+            eprintln!("⚠️ 纸张预设解析失败：{}，将使用内置预设", e);
+            default_paper_presets()
+        }
+    }
+}
+
+// 按页面尺寸查找预设名称，横竖向均可匹配，找不到时返回“自定义”
+pub fn find_paper_preset(presets: &[PaperPreset], width_mm: f32, height_mm: f32) -> String {
+    let (short_mm, long_mm) = if width_mm < height_mm { (width_mm, height_mm) } else { (height_mm, width_mm) };
+    presets
+        .iter()
+        .find(|p| {
+            let (p_short, p_long) = if p.width_mm < p.height_mm { (p.width_mm, p.height_mm) } else { (p.height_mm, p.width_mm) };
+            (p_short - short_mm).abs() < 0.5 && (p_long - long_mm).abs() < 0.5
+        })
+        .map(|p| p.name.clone())
+        .unwrap_or_else(|| PAPER_CUSTOM.to_string())
+}
+
 pub fn color_to_rgb(color: &str) -> Color {    
     match color {
         "白" => Color::Rgb(Rgb::new(1.0, 1.0, 1.0, None)),
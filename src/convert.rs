@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use crate::config::*;
+
+// 内置词典，编译时打包进程序，离线可用
+// 格式与 OpenCC 相同：每行“原文<TAB>候选1 候选2 …”，取第一个候选
+const ST_CHARACTERS: &str = include_str!("../dict/STCharacters.txt");
+const ST_PHRASES: &str = include_str!("../dict/STPhrases.txt");
+const TS_CHARACTERS: &str = include_str!("../dict/TSCharacters.txt");
+const TS_PHRASES: &str = include_str!("../dict/TSPhrases.txt");
+
+/// 简繁转换器：按最长匹配先查词组再查单字，用户对照表优先于内置词典
+pub struct Converter {
+    dict: HashMap<String, String>,
+    max_len: usize,
+}
+
+impl Converter {
+    /// mode 为“简转繁”或“繁转简”，其他值返回 None 表示不转换
+    pub fn new(mode: &str, user_table_path: &str) -> Option<Self> {
+        let builtin: [&str; 2] = match mode {
+            "简转繁" => [ST_CHARACTERS, ST_PHRASES],
+            "繁转简" => [TS_CHARACTERS, TS_PHRASES],
+            _ => return None,
+        };
+        let mut converter = Converter::empty();
+        for table in builtin {
+            converter.add_table(table);
+        }
+        // 用户对照表用于处理一简对多繁等歧义，后加入以覆盖内置词条
+        match fs::read_to_string(Path::new(user_table_path)) {
+            Ok(table) => converter.add_table(&table),
+            Err(e) => eprintln!("⚠️ 用户对照表读取失败：{}，仅使用内置词典", e),
+        }
+        Some(converter)
+    }
+
+    /// 由用户替换表生成，同样按最长匹配替换
+    pub fn from_substitutions(substitutions: &[Substitution]) -> Self {
+        let mut converter = Converter::empty();
+        for sub in substitutions.iter().filter(|sub| !sub.from.is_empty()) {
+            converter.insert(&sub.from, &sub.to);
+        }
+        converter
+    }
+
+    fn empty() -> Self {
+        Converter { dict: HashMap::new(), max_len: 1 }
+    }
+
+    fn insert(&mut self, key: &str, value: &str) {
+        self.max_len = self.max_len.max(key.chars().count());
+        self.dict.insert(key.to_string(), value.to_string());
+    }
+
+    fn add_table(&mut self, table: &str) {
+        for line in table.lines() {
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, values)) = line.split_once('\t') else {
+                continue;
+            };
+            let Some(value) = values.split_whitespace().next() else {
+                continue;
+            };
+            self.insert(key, value);
+        }
+    }
+
+    pub fn convert(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut result = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let longest = self.max_len.min(chars.len() - i);
+            let matched = (1..=longest).rev().find_map(|len| {
+                let key: String = chars[i..i + len].iter().collect();
+                self.dict.get(&key).map(|value| (len, value))
+            });
+            match matched {
+                Some((len, value)) => {
+                    result.push_str(value);
+                    i += len;
+                }
+                None => {
+                    result.push(chars[i]);
+                    i += 1;
+                }
+            }
+        }
+        result
+    }
+}
+
+/// 按繁体、简体专用字的多少判断文字是繁体还是简体，返回 BCP 47 语言标签；
+/// 两者都没有时按古籍惯例视为繁体
+pub fn detect_language(text: &str) -> &'static str {
+    let converted = |table: &str| -> HashSet<char> {
+        table
+            .lines()
+            .filter_map(|line| {
+                let (key, values) = line.split_once('\t')?;
+                // 候选中含原字的两体通用（如“干”），不作判断依据
+                values.split_whitespace().all(|value| value != key).then(|| key.chars().next()).flatten()
+            })
+            .collect()
+    };
+    let traditional = converted(TS_CHARACTERS);
+    let simplified = converted(ST_CHARACTERS);
+    let (mut hant, mut hans) = (0usize, 0usize);
+    for c in text.chars() {
+        if traditional.contains(&c) {
+            hant += 1;
+        } else if simplified.contains(&c) {
+            hans += 1;
+        }
+    }
+    if hans > hant { "zh-Hans" } else { "zh-Hant" }
+}
@@ -0,0 +1,186 @@
+// 文档树：由纯文本或 .vr 标记文本解析而来，排版阶段按块分行分页
+//
+// .vr 标记（每行一块，行首标记后跟一个空格）：
+//   # 章名            新起一章
+//   ## 节名           节标题，低一格
+//   + 正文 / +2 正文  低一格 / 低二格的段落，排版时低格不超过列长
+//   ^ 正文 / ^2 正文  抬头一格 / 二格的段落，高出版框，至多抬头 MAX_RAISE 格
+//   > 引文            引文，低二格
+//   * 注文            注文，低三格，排小字
+//   ---               换页
+//   \               空一行
+//   其他非空行为普通段落；% 开头的行为注释
+
+use crate::config::*;
+
+/// 一个排版块
+pub enum Block {
+    Heading { level: u8, text: String },
+    Paragraph { text: String, indent: i32 }, // indent 正数为低格，负数为抬头
+    Quote { text: String },
+    Note { text: String },
+    PageBreak,
+    BlankLine,
+}
+
+pub struct Chapter {
+    pub title: Option<String>,
+    pub blocks: Vec<Block>,
+}
+
+pub struct Document {
+    pub chapters: Vec<Chapter>,
+}
+
+impl Block {
+    /// 块的文字和缩进格数，换页、空行没有文字
+    pub fn text_and_indent(&self) -> Option<(&str, i32)> {
+        match self {
+            Block::Heading { level, text } => Some((text, if *level <= 1 { 0 } else { SECTION_INDENT })),
+            Block::Paragraph { text, indent } => Some((text, *indent)),
+            Block::Quote { text } => Some((text, QUOTE_INDENT)),
+            Block::Note { text } => Some((text, NOTE_INDENT)),
+            Block::PageBreak | Block::BlankLine => None,
+        }
+    }
+}
+
+impl Document {
+    /// 按文件扩展名选择解析方式：.vr 按标记解析，其余（含 .md）按纯文本
+    pub fn parse(text: &str, path: &str) -> Self {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        match extension.as_str() {
+            "vr" => Self::from_markup(text),
+            _ => Self::from_plain_text(text),
+        }
+    }
+
+    /// 纯文本：%% 分章，每行一段
+    pub fn from_plain_text(text: &str) -> Self {
+        let chapters = text
+            .split("%%")
+            .map(|chapter| Chapter {
+                title: None,
+                blocks: chapter
+                    .lines()
+                    .map(|line| Block::Paragraph { text: line.to_string(), indent: 0 })
+                    .collect(),
+            })
+            .collect();
+        Document { chapters }
+    }
+
+    /// .vr 标记文本
+    pub fn from_markup(text: &str) -> Self {
+        let mut chapters: Vec<Chapter> = Vec::new();
+        let mut current = Chapter { title: None, blocks: Vec::new() };
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+            if line.starts_with('%') {
+                continue;
+            }
+            if let Some(title) = line.strip_prefix("# ") {
+                // 新起一章，开头没有内容时不留空章
+                if current.title.is_some() || !current.blocks.is_empty() {
+                    chapters.push(current);
+                }
+                current = Chapter { title: Some(title.trim().to_string()), blocks: Vec::new() };
+                current.blocks.push(Block::Heading { level: 1, text: title.trim().to_string() });
+                continue;
+            }
+            if let Some(block) = parse_block(line) {
+                current.blocks.push(block);
+            }
+        }
+        chapters.push(current);
+        Document { chapters }
+    }
+
+    /// 转换各块文字（简繁、数字等），不改动标记
+    pub fn map_text(&mut self, convert: impl Fn(&str) -> String) {
+        for chapter in &mut self.chapters {
+            if let Some(title) = &mut chapter.title {
+                *title = convert(title);
+            }
+            for block in &mut chapter.blocks {
+                match block {
+                    Block::Heading { text, .. }
+                    | Block::Paragraph { text, .. }
+                    | Block::Quote { text }
+                    | Block::Note { text } => *text = convert(text),
+                    Block::PageBreak | Block::BlankLine => {}
+                }
+            }
+        }
+    }
+}
+
+fn parse_block(line: &str) -> Option<Block> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if trimmed == "---" {
+        return Some(Block::PageBreak);
+    }
+    if trimmed == "\\" {
+        return Some(Block::BlankLine);
+    }
+    if let Some(rest) = line.strip_prefix("## ") {
+        return Some(Block::Heading { level: 2, text: rest.trim().to_string() });
+    }
+    if let Some(rest) = line.strip_prefix("> ") {
+        return Some(Block::Quote { text: rest.to_string() });
+    }
+    if let Some(rest) = line.strip_prefix("* ") {
+        return Some(Block::Note { text: rest.to_string() });
+    }
+    for (mark, sign) in [('+', 1), ('^', -1)] {
+        if let Some(rest) = line.strip_prefix(mark) {
+            if let Some((count, text)) = rest.split_once(' ') {
+                // 格数只能是数字，+-5、++ 之类不是标记
+                let count = match count {
+                    "" => Some(1),
+                    _ if count.chars().all(|c| c.is_ascii_digit()) => count.parse::<i32>().ok(),
+                    _ => None,
+                };
+                if let Some(count) = count {
+                    let count = if sign < 0 { count.min(MAX_RAISE) } else { count };
+                    return Some(Block::Paragraph { text: text.to_string(), indent: sign * count });
+                }
+            }
+        }
+    }
+    Some(Block::Paragraph { text: line.to_string(), indent: 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indents(text: &str) -> Vec<i32> {
+        Document::from_markup(text).chapters[0]
+            .blocks
+            .iter()
+            .filter_map(|block| block.text_and_indent().map(|(_, indent)| indent))
+            .collect()
+    }
+
+    #[test]
+    fn indent_counts() {
+        assert_eq!(indents("+ 甲\n+2 乙\n^ 丙\n^2 丁"), vec![1, 2, -1, -2]);
+        // 抬头有上限，带符号的格数不是标记
+        assert_eq!(indents(&format!("^{} 甲", MAX_RAISE + 5)), vec![-MAX_RAISE]);
+        assert_eq!(indents("+-5 甲\n^+2 乙"), vec![0, 0]);
+    }
+
+    #[test]
+    fn parse_by_extension() {
+        assert_eq!(Document::parse("# 卷一\n正文", "a.vr").chapters[0].title.as_deref(), Some("卷一"));
+        assert!(Document::parse("# 卷一\n正文", "a.md").chapters[0].title.is_none());
+    }
+}
@@ -0,0 +1,326 @@
+// EPUB 导入：按书脊顺序读出各章，章名取自目录（nav 或 NCX），去掉标记后保留段落
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// EPUB 读出的内容，text 为 %% 分章、每章首行为章名的纯文本
+pub struct EpubBook {
+    pub title: String,
+    pub author: String,
+    pub text: String,
+}
+
+/// 输入路径是否为 EPUB
+pub fn is_epub(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("epub"))
+}
+
+pub fn read_epub(path: &str) -> Result<EpubBook, Box<dyn Error>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    // 步骤1：container.xml 指出 OPF 文件位置
+    let container = read_entry(&mut archive, "META-INF/container.xml")?;
+    let opf_path = tags(&container, "rootfile")
+        .find_map(|tag| attr(tag, "full-path"))
+        .ok_or("container.xml 中没有 rootfile")?;
+    let opf = read_entry(&mut archive, &opf_path)?;
+    let opf_dir = parent_dir(&opf_path);
+
+    // 步骤2：元数据和清单
+    let title = element_text(&opf, "dc:title").unwrap_or_default();
+    let author = element_text(&opf, "dc:creator").unwrap_or_default();
+    let mut manifest: HashMap<String, String> = HashMap::new();
+    let mut nav_path = None;
+    let mut ncx_path = None;
+    for item in tags(&opf, "item") {
+        let (Some(id), Some(href)) = (attr(item, "id"), attr(item, "href")) else {
+            continue;
+        };
+        let full_path = join_path(&opf_dir, &href);
+        if attr(item, "properties").is_some_and(|p| p.split_whitespace().any(|p| p == "nav")) {
+            nav_path = Some(full_path.clone());
+        }
+        if attr(item, "media-type").as_deref() == Some("application/x-dtbncx+xml") {
+            ncx_path = Some(full_path.clone());
+        }
+        manifest.insert(id, full_path);
+    }
+
+    // 步骤3：章名，EPUB3 取 nav，EPUB2 取 NCX
+    let mut titles: HashMap<String, String> = HashMap::new();
+    if let Some(nav_path) = &nav_path {
+        let nav = read_entry(&mut archive, nav_path)?;
+        let nav_dir = parent_dir(nav_path);
+        for (href, label) in anchors(&nav) {
+            titles.entry(join_path(&nav_dir, &href)).or_insert(label);
+        }
+    } else if let Some(ncx_path) = &ncx_path {
+        let ncx = read_entry(&mut archive, ncx_path)?;
+        let ncx_dir = parent_dir(ncx_path);
+        for point in ncx.split("<navPoint").skip(1) {
+            let (Some(label), Some(src)) = (element_text(point, "text"), tags(point, "content").find_map(|tag| attr(tag, "src"))) else {
+                continue;
+            };
+            titles.entry(join_path(&ncx_dir, &src)).or_insert(label);
+        }
+    }
+
+    // 步骤4：按书脊顺序转为纯文本，每个 XHTML 为一章
+    let mut chapters = Vec::new();
+    for itemref in tags(&opf, "itemref") {
+        let Some(path) = attr(itemref, "idref").and_then(|id| manifest.get(&id)) else {
+            continue;
+        };
+        if nav_path.as_ref() == Some(path) {
+            continue;
+        }
+        let xhtml = read_entry(&mut archive, path)?;
+        let mut paragraphs = xhtml_to_paragraphs(&xhtml);
+        if let Some(title) = titles.get(path) {
+            // 正文开头常重复章名，只保留一处
+            if paragraphs.first() != Some(title) {
+                paragraphs.insert(0, title.clone());
+            }
+        }
+        if !paragraphs.is_empty() {
+            chapters.push(paragraphs.join("\n"));
+        }
+    }
+    Ok(EpubBook { title, author, text: chapters.join("\n%%\n") })
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<String, Box<dyn Error>> {
+    let mut entry = archive.by_name(name)?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn parent_dir(path: &str) -> String {
+    match path.rfind('/') {
+        Some(i) => path[..=i].to_string(),
+        None => String::new(),
+    }
+}
+
+// 相对路径转为压缩包内路径，去掉锚点并处理 ../ 与 %20 等转义
+fn join_path(dir: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or("");
+    let mut parts: Vec<String> = Vec::new();
+    for part in format!("{}{}", dir, percent_decode(href)).split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part.to_string()),
+        }
+    }
+    parts.join("/")
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// 找出所有名为 name 的开始标签（含属性），不匹配名字以 name 开头的其他标签
+fn tags<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    xml.match_indices('<').filter_map(move |(start, _)| {
+        let rest = &xml[start + 1..];
+        let after = rest.strip_prefix(name)?;
+        if !after.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>') {
+            return None;
+        }
+        let end = rest.find('>')?;
+        Some(&rest[..end])
+    })
+}
+
+fn attr(tag: &str, name: &str) -> Option<String> {
+    for quote in ['"', '\''] {
+        let pattern = format!("{}={}", name, quote);
+        let mut search = tag;
+        while let Some(i) = search.find(&pattern) {
+            // 属性名前须为空白，避免 id 匹配到 idref
+            let preceded = search[..i].chars().next_back().is_some_and(char::is_whitespace);
+            let value = &search[i + pattern.len()..];
+            if preceded {
+                return value.find(quote).map(|end| decode_entities(&value[..end]));
+            }
+            search = value;
+        }
+    }
+    None
+}
+
+// 元素的文字内容，去掉内部标记
+fn element_text(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}", name);
+    let start = xml.match_indices(&open).find_map(|(i, _)| {
+        let rest = &xml[i + open.len()..];
+        if !rest.starts_with(|c: char| c.is_whitespace() || c == '>') {
+            return None;
+        }
+        Some(i + open.len() + rest.find('>')? + 1)
+    })?;
+    let end = xml[start..].find(&format!("</{}>", name))? + start;
+    let text = strip_tags(&xml[start..end]);
+    let text = join_words(&text);
+    (!text.is_empty()).then_some(text)
+}
+
+// nav 中的目录链接：(href, 链接文字)
+fn anchors(nav: &str) -> Vec<(String, String)> {
+    let toc = nav
+        .split("<nav")
+        .skip(1)
+        .find(|part| part.split('>').next().is_some_and(|tag| tag.contains("toc")))
+        .unwrap_or(nav);
+    toc.split("<a")
+        .skip(1)
+        .filter(|part| part.starts_with(|c: char| c.is_whitespace() || c == '>'))
+        .filter_map(|part| {
+            let tag_end = part.find('>')?;
+            let href = attr(&part[..tag_end], "href")?;
+            let end = part.find("</a>")?;
+            let label = join_words(&strip_tags(&part[tag_end + 1..end]));
+            (!label.is_empty()).then_some((href, label))
+        })
+        .collect()
+}
+
+// XHTML 转为段落：块级标签处分段，去掉其余标记和 head、script、style
+fn xhtml_to_paragraphs(xhtml: &str) -> Vec<String> {
+    let body = match xhtml.find("<body") {
+        Some(i) => &xhtml[i..],
+        None => xhtml,
+    };
+    let mut text = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>').map(|end| start + end) else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..end];
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        rest = &rest[end + 1..];
+        if !tag.starts_with('/') && (name == "script" || name == "style") {
+            // 跳过脚本、样式内容
+            let close = format!("</{}", name);
+            rest = rest.find(&close).map(|i| &rest[i..]).unwrap_or("");
+            continue;
+        }
+        if matches!(name.as_str(), "p" | "div" | "br" | "li" | "tr" | "blockquote" | "section" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6") {
+            text.push(PARAGRAPH_BREAK);
+        }
+    }
+    text.push_str(rest);
+    // 源文件中的换行只是排版，段落只在块级标签处断开
+    decode_entities(&text)
+        .split(PARAGRAPH_BREAK)
+        .map(join_words)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+const PARAGRAPH_BREAK: char = '\u{2029}';
+
+// 合并空白：拉丁文字之间留一个空格，与汉字相邻的空白去掉
+fn join_words(text: &str) -> String {
+    let mut joined = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        let ascii_gap = joined.chars().next_back().is_some_and(|c| c.is_ascii())
+            && word.chars().next().is_some_and(|c| c.is_ascii());
+        if ascii_gap {
+            joined.push(' ');
+        }
+        joined.push_str(word);
+    }
+    joined
+}
+
+fn strip_tags(xml: &str) -> String {
+    let mut text = String::with_capacity(xml.len());
+    let mut in_tag = false;
+    for c in xml.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    decode_entities(&text)
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').filter(|end| *end <= 10).map(|end| &rest[1..end]);
+        let value = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" | "ensp" | "emsp" | "thinsp" => Some(' '),
+            "hellip" => Some('…'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "middot" => Some('·'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        });
+        match (entity, value) {
+            (Some(entity), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
@@ -0,0 +1,371 @@
+// EPUB3 导出：与 PDF 共用文档树、替换表和标点处理，按竖排从右向左翻页
+use crate::config::*;
+use crate::convert::{self, Converter};
+use crate::document::{Block, Chapter};
+use crate::metrics;
+use crate::numeral;
+use crate::ruby;
+use crate::utils::{self, LatinLayout};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const COVER_IMAGE: &[u8] = include_bytes!("./cover.png");
+const FONT_FAMILY: &str = "BookFont";
+const NAV_TITLE_CHARS: usize = 16; // 无章名时取首段作目录标题的字数
+
+/// 生成 EPUB，返回输出路径（与 PDF 同名，扩展名为 .epub）和字体嵌入情况的说明
+pub fn create_epub(fileinfo: &FileInfo, typeset: &Typeset) -> Result<(String, String), Box<dyn Error>> {
+    let mut fileinfo = fileinfo.clone();
+    let doc = utils::load_document(&mut fileinfo, typeset)?;
+    let substituter = Converter::from_substitutions(&typeset.substitutions);
+    let book_name = substituter.convert(&numeral::convert_numbers(&fileinfo.name, typeset.number_mode.as_str()));
+    let author = fileinfo.author.trim().to_string();
+    // 与 PDF 相同：转换后按目标字体，不转换时由正文用字判断
+    let language = match typeset.script_convert.as_str() {
+        "简转繁" => "zh-Hant",
+        "繁转简" => "zh-Hans",
+        _ => {
+            let text: String = doc
+                .chapters
+                .iter()
+                .flat_map(|chapter| chapter.blocks.iter())
+                .filter_map(|block| block.text_and_indent().map(|(text, _)| text))
+                .collect();
+            convert::detect_language(&text)
+        }
+    };
+
+    // 每章一个 XHTML，空章不输出
+    let mut chapters: Vec<(String, String)> = Vec::new();
+    for chapter in &doc.chapters {
+        let body = chapter_body(chapter, typeset, &substituter);
+        if body.is_empty() {
+            continue;
+        }
+        let title = chapter_title(chapter, chapters.len() + 1, typeset, &substituter);
+        chapters.push((title, body));
+    }
+    let nav = nav_xhtml(&book_name, language, &chapters);
+    let cover = xhtml_page(&book_name, language, &cover_body(&book_name));
+    let pages: Vec<String> = chapters.iter().map(|(title, body)| xhtml_page(title, language, body)).collect();
+
+    // 主字体按书中用字精简后嵌入，字体集合（ttc）取第一个字体；CFF 轮廓无法精简，ttf/otf 整套嵌入
+    let font_extension = Path::new(&fileinfo.main_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let (font, font_note) = match font_extension.as_str() {
+        "ttf" | "otf" | "ttc" => {
+            let font_bytes = std::fs::read(&fileinfo.main_path)?;
+            let text: String = [nav.as_str(), cover.as_str()].into_iter().chain(pages.iter().map(String::as_str)).collect();
+            let megabytes = |bytes: &[u8]| bytes.len() as f64 / 1048576.0;
+            match (metrics::subset_font(&font_bytes, 0, &text), font_extension.as_str()) {
+                (Some(subset), _) => {
+                    let note = format!("主字体按书中用字精简后嵌入（{:.1} MB → {:.1} MB）", megabytes(&font_bytes), megabytes(&subset));
+                    (Some(("fonts/main.ttf", "font/ttf", subset)), note)
+                }
+                (None, "ttc") => (None, "主字体集合不是 TrueType 轮廓，无法精简，未嵌入字体".to_string()),
+                (None, extension) => {
+                    let note = format!("主字体不是 TrueType 轮廓，无法精简，整套嵌入（{:.1} MB），文件较大", megabytes(&font_bytes));
+                    let path = if extension == "otf" { ("fonts/main.otf", "font/otf") } else { ("fonts/main.ttf", "font/ttf") };
+                    (Some((path.0, path.1, font_bytes)), note)
+                }
+            }
+        }
+        _ => (None, "主字体不是 ttf/otf/ttc，未嵌入字体".to_string()),
+    };
+    let font_item = font.as_ref().map(|(path, media_type, _)| (*path, *media_type));
+    let identifier = book_identifier(&book_name, &author, fileinfo.edition.trim());
+
+    let output_path = Path::new(&fileinfo.outputpath).with_extension("epub");
+    let mut zip = ZipWriter::new(File::create(&output_path)?);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // mimetype 须为第一项且不压缩
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(package_opf(&book_name, &author, &identifier, language, font_item, chapters.len()).as_bytes())?;
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(nav.as_bytes())?;
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(style_css(font_item.map(|(path, _)| path)).as_bytes())?;
+    zip.start_file("OEBPS/cover.xhtml", deflated)?;
+    zip.write_all(cover.as_bytes())?;
+    zip.start_file("OEBPS/images/cover.png", stored)?;
+    zip.write_all(COVER_IMAGE)?;
+    if let Some((path, _, font_bytes)) = &font {
+        zip.start_file(format!("OEBPS/{}", path), deflated)?;
+        zip.write_all(font_bytes)?;
+    }
+    for (i, page) in pages.iter().enumerate() {
+        zip.start_file(format!("OEBPS/{}", chapter_file(i)), deflated)?;
+        zip.write_all(page.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok((output_path.to_string_lossy().into_owned(), font_note))
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn chapter_file(index: usize) -> String {
+    format!("chapter{:03}.xhtml", index + 1)
+}
+
+fn package_opf(title: &str, author: &str, identifier: &str, language: &str, font: Option<(&str, &str)>, chapter_count: usize) -> String {
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    manifest.push_str("    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n");
+    manifest.push_str("    <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n");
+    manifest.push_str("    <item id=\"cover-image\" href=\"images/cover.png\" media-type=\"image/png\" properties=\"cover-image\"/>\n");
+    manifest.push_str("    <item id=\"cover\" href=\"cover.xhtml\" media-type=\"application/xhtml+xml\"/>\n");
+    spine.push_str("    <itemref idref=\"cover\" linear=\"no\"/>\n");
+    if let Some((path, media_type)) = font {
+        manifest.push_str(&format!("    <item id=\"font\" href=\"{}\" media-type=\"{}\"/>\n", path, media_type));
+    }
+    for i in 0..chapter_count {
+        manifest.push_str(&format!(
+            "    <item id=\"chapter{:03}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            i + 1,
+            chapter_file(i)
+        ));
+        spine.push_str(&format!("    <itemref idref=\"chapter{:03}\"/>\n", i + 1));
+    }
+    let creator = if author.is_empty() {
+        String::new()
+    } else {
+        format!("    <dc:creator>{}</dc:creator>\n", utils::escape_xml(author))
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="bookid" xml:lang="{language}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="bookid">{identifier}</dc:identifier>
+    <dc:title>{title}</dc:title>
+{creator}    <dc:language>{language}</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+    <meta name="cover" content="cover-image"/>
+  </metadata>
+  <manifest>
+{manifest}  </manifest>
+  <spine page-progression-direction="rtl">
+{spine}  </spine>
+</package>
+"#,
+        language = language,
+        identifier = identifier,
+        title = utils::escape_xml(title),
+        creator = creator,
+        modified = utc_timestamp(),
+        manifest = manifest,
+        spine = spine,
+    )
+}
+
+fn nav_xhtml(title: &str, language: &str, chapters: &[(String, String)]) -> String {
+    let items: String = chapters
+        .iter()
+        .enumerate()
+        .map(|(i, (chapter_title, _))| format!("      <li><a href=\"{}\">{}</a></li>\n", chapter_file(i), utils::escape_xml(chapter_title)))
+        .collect();
+    let body = format!("  <nav epub:type=\"toc\" id=\"toc\">\n    <h1>目录</h1>\n    <ol>\n{}    </ol>\n  </nav>\n", items);
+    xhtml_page(title, language, &body)
+}
+
+fn xhtml_page(title: &str, language: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{language}" lang="{language}">
+<head>
+  <meta charset="UTF-8"/>
+  <title>{title}</title>
+  <link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+        language = language,
+        title = utils::escape_xml(title),
+        body = body,
+    )
+}
+
+// 竖排样式：低格用 margin-top（竖排时为行首方向），纵中横、直立数字与 PDF 一致
+fn style_css(font_path: Option<&str>) -> String {
+    let font_face = match font_path {
+        Some(path) => format!("@font-face {{\n  font-family: \"{}\";\n  src: url(\"{}\");\n}}\n", FONT_FAMILY, path),
+        None => String::new(),
+    };
+    format!(
+        r#"{font_face}html {{
+  writing-mode: vertical-rl;
+  -epub-writing-mode: vertical-rl;
+  -webkit-writing-mode: vertical-rl;
+}}
+body {{
+  font-family: "{family}", serif;
+  line-height: 1.8;
+  margin: 0;
+}}
+p {{ margin: 0; text-indent: 0; }}
+h1 {{ font-size: 1.4em; margin: 0 0 0 1em; }}
+h2 {{ font-size: 1.2em; margin: {section}em 0 0 0.5em; }}
+p.quote {{ margin-top: {quote}em; }}
+p.note {{ margin-top: {note}em; font-size: 0.85em; }}
+div.pagebreak {{ break-after: page; page-break-after: always; }}
+rt {{ font-size: 0.5em; }}
+.upright {{ text-orientation: upright; -epub-text-orientation: upright; -webkit-text-orientation: upright; }}
+.tcy {{ text-combine-upright: all; -epub-text-combine: horizontal; -webkit-text-combine: horizontal; }}
+div.cover {{ position: relative; height: 100%; margin: 0; }}
+div.cover img {{ height: 100%; }}
+div.cover p {{ position: absolute; top: 10%; right: 15%; font-size: 2em; writing-mode: vertical-rl; }}
+"#,
+        font_face = font_face,
+        family = FONT_FAMILY,
+        section = SECTION_INDENT,
+        quote = QUOTE_INDENT,
+        note = NOTE_INDENT,
+    )
+}
+
+// 封面：与 PDF 相同的封面图，书名竖排在右上
+fn cover_body(title: &str) -> String {
+    format!(
+        "<div class=\"cover\">\n  <img src=\"images/cover.png\" alt=\"{0}\"/>\n  <p>{0}</p>\n</div>\n",
+        utils::escape_xml(title)
+    )
+}
+
+fn chapter_body(chapter: &Chapter, typeset: &Typeset, substituter: &Converter) -> String {
+    let mut body = String::new();
+    let mut has_text = false;
+    for block in &chapter.blocks {
+        // 纯文本中的空行不成段
+        if let Some((text, _)) = block.text_and_indent() {
+            if text.trim().is_empty() {
+                continue;
+            }
+            has_text = true;
+        }
+        let html = match block {
+            Block::Heading { level, text } => {
+                let tag = if *level <= 1 { "h1" } else { "h2" };
+                format!("<{0}>{1}</{0}>", tag, inline_html(text, typeset, substituter))
+            }
+            // 流式排版没有版框，抬头的段落不再高出
+            Block::Paragraph { text, indent } if *indent > 0 => {
+                format!("<p style=\"margin-top: {}em\">{}</p>", indent, inline_html(text, typeset, substituter))
+            }
+            Block::Paragraph { text, .. } => format!("<p>{}</p>", inline_html(text, typeset, substituter)),
+            Block::Quote { text } => format!("<p class=\"quote\">{}</p>", inline_html(text, typeset, substituter)),
+            Block::Note { text } => format!("<p class=\"note\">{}</p>", inline_html(text, typeset, substituter)),
+            Block::PageBreak => "<div class=\"pagebreak\"></div>".to_string(),
+            Block::BlankLine => "<p>&#160;</p>".to_string(),
+        };
+        body.push_str(&html);
+        body.push('\n');
+    }
+    if has_text { body } else { String::new() }
+}
+
+// 目录标题：章名，无章名时取首段开头，再无则按序号
+fn chapter_title(chapter: &Chapter, number: usize, typeset: &Typeset, substituter: &Converter) -> String {
+    let text = chapter.title.as_deref().or_else(|| {
+        chapter
+            .blocks
+            .iter()
+            .filter_map(|block| block.text_and_indent())
+            .map(|(text, _)| text)
+            .find(|text| !text.trim().is_empty())
+    });
+    let plain: String = match text {
+        Some(text) => {
+            let prepared: String = utils::prepare_text(text.trim(), typeset, substituter).into_iter().collect();
+            ruby::strip_markup(&prepared).chars().take(NAV_TITLE_CHARS).collect()
+        }
+        None => String::new(),
+    };
+    if plain.is_empty() {
+        format!("第{}章", numeral::convert_numbers(&number.to_string(), "按上下文"))
+    } else {
+        plain
+    }
+}
+
+// 正文转为 XHTML：替换表和标点处理与 PDF 相同，注音转为 ruby，横排片段按 PDF 的排法标注
+fn inline_html(text: &str, typeset: &Typeset, substituter: &Converter) -> String {
+    let chars = utils::prepare_text(text, typeset, substituter);
+    let mut html = String::with_capacity(text.len() * 2);
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some((base, gloss, len)) = ruby::parse_ruby(&chars[i..]) {
+            html.push_str(&format!("<ruby>{}<rt>{}</rt></ruby>", utils::escape_xml(&base), utils::escape_xml(&gloss)));
+            i += len;
+            continue;
+        }
+        if utils::is_latin(chars[i]) {
+            let end = utils::latin_run_end(&chars, i);
+            let run: String = chars[i..end].iter().collect();
+            match utils::latin_layout(&run) {
+                LatinLayout::Upright => html.push_str(&format!("<span class=\"upright\">{}</span>", utils::escape_xml(&run))),
+                LatinLayout::TateChuYoko => html.push_str(&format!("<span class=\"tcy\">{}</span>", utils::escape_xml(&run))),
+                LatinLayout::Rotated => html.push_str(&utils::escape_xml(&run)),
+            }
+            i = end;
+            continue;
+        }
+        html.push_str(&utils::escape_xml(&chars[i].to_string()));
+        i += 1;
+    }
+    html
+}
+
+// 书的唯一标识：由书名、作者、版本散列出 UUID 形式的编号，重新导出同一本书时不变，阅读器据此更新而不是另存；
+// 散列用 FNV-1a，结果不随编译器版本变化
+fn book_identifier(title: &str, author: &str, edition: &str) -> String {
+    let key = format!("{}\u{1f}{}\u{1f}{}", title, author, edition);
+    let fnv = |basis: u64| key.bytes().fold(basis, |hash, b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    let high = fnv(0xcbf2_9ce4_8422_2325);
+    let low = fnv(high);
+    format!(
+        "urn:uuid:{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xfff,
+        0x8000 | ((low >> 48) & 0x3fff),
+        low & 0xffff_ffff_ffff
+    )
+}
+
+// dcterms:modified 要求的 UTC 时间，如 2024-01-02T03:04:05Z
+fn utc_timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) as i64;
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // 公历日期换算（Howard Hinnant 算法）
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
@@ -0,0 +1,382 @@
+// 印刷拼版：在排好的页面外加出血与印刷标记，放到加大的印张上
+use printpdf::*;
+use crate::config::*;
+use crate::pdfoption::PagedBook;
+
+/// 线装印刷：每页放到加大的印张上，四周按出血扩展底色，加裁切线和套准标记；
+/// 页面有折页位置（book.fold_x_mm）时在此加折页线；
+/// 出血底色画在背景层，印刷标记单独成层，隐藏标记层时出血仍在
+pub fn add_print_marks(book: &mut PagedBook, bleed_mm: f32, creator: &str) {
+    let bleed_mm = bleed_mm.max(0.0);
+    // 标记区：出血外留间距，再放裁切线
+    let slug_mm = bleed_mm + PRINT_MARK_GAP_MM + PRINT_MARK_LENGTH_MM;
+    let trim_width_mm = book.page_width_mm;
+    let trim_height_mm = book.page_height_mm;
+    let sheet_width_mm = trim_width_mm + slug_mm * 2.0;
+    let sheet_height_mm = trim_height_mm + slug_mm * 2.0;
+
+    let marks_layer = Layer {
+        name: "Print Marks".to_string(),
+        creator: creator.to_string(),
+        intent: LayerIntent::Design,
+        usage: LayerSubtype::Artwork,
+    };
+    let marks_layer_id = book.doc.add_layer(&marks_layer);
+    book.layers.push((marks_layer_id.clone(), marks_layer.name.clone()));
+
+    let trim = Trim {
+        left: Mm(slug_mm).into_pt(),
+        bottom: Mm(slug_mm).into_pt(),
+        right: Mm(slug_mm + trim_width_mm).into_pt(),
+        top: Mm(slug_mm + trim_height_mm).into_pt(),
+    };
+    let bleed = Mm(bleed_mm).into_pt();
+    let bleed_ops = add_bleed(&trim, bleed, &book.bg_color, &book.bg_layer);
+    let mark_ops = add_marks(&trim, bleed, book.fold_x_mm.map(|x| trim.left + Mm(x).into_pt()), &marks_layer_id);
+
+    let pages = std::mem::take(&mut book.pages);
+    book.pages = pages
+        .into_iter()
+        .map(|page| {
+            let mut ops = bleed_ops.clone();
+            ops.extend(page.ops.into_iter().map(|op| offset_op(op, trim.left, trim.bottom)));
+            ops.extend(mark_ops.iter().cloned());
+            PdfPage::new(Mm(sheet_width_mm), Mm(sheet_height_mm), ops)
+        })
+        .collect();
+    book.page_width_mm = sheet_width_mm;
+    book.page_height_mm = sheet_height_mm;
+    book.fold_x_mm = book.fold_x_mm.map(|x| x + slug_mm);
+}
+
+/// 纵向单页拼版：按右翻装订的书帖顺序把页面排到加大的印张上，每张印张依次输出正、反两面；
+/// signature_pages 为每帖页数，None 时全书为一帖（骑马订）；rows 为印张上下排放的对页数，
+/// 四拼时印张印好后横向裁开，下半叠放入上半之内再对折；拼好的印张对折于正中
+pub fn impose_signatures(book: &mut PagedBook, signature_pages: Option<usize>, rows: usize) {
+    let rows = rows.max(1);
+    let page_width_mm = book.page_width_mm;
+    let page_height_mm = book.page_height_mm;
+    let sheet_width_mm = page_width_mm * 2.0;
+    let sheet_height_mm = page_height_mm * rows as f32;
+    // 每张印张一面有 rows 个对页，正反两面共 rows * 4 页
+    let sheet_pages = rows * 4;
+    let signature_pages = signature_pages
+        .unwrap_or(book.pages.len())
+        .div_ceil(sheet_pages)
+        .max(1)
+        * sheet_pages;
+    let total = book.pages.len().div_ceil(signature_pages) * signature_pages;
+    // 空白页补足整帖，只铺底色
+    let blank = add_blank(page_width_mm, page_height_mm, &book.bg_color);
+    let mut pages: Vec<Option<Vec<Op>>> = std::mem::take(&mut book.pages).into_iter().map(|page| Some(page.ops)).collect();
+    pages.resize_with(total, || None);
+
+    let mut sheets = Vec::new();
+    for base in (0..total).step_by(signature_pages) {
+        // 一帖内按对折小册排序：第 s 张对折纸正面为（首起第 2s 页，末起第 2s 页），反面相反
+        let folds = signature_pages / 4;
+        let per_row = folds / rows;
+        for sheet in 0..per_row {
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+            for row in 0..rows {
+                let s = sheet + row * per_row;
+                let first = base + 2 * s;
+                let last = base + signature_pages - 1 - 2 * s;
+                // 右翻：正面左为前页、右为后页；翻面后左右互换
+                front.push((row, first, last));
+                back.push((row, last - 1, first + 1));
+            }
+            for side in [front, back] {
+                let mut ops = Vec::new();
+                for (row, left, right) in side {
+                    // 第一排在印张最上方
+                    let y = Mm(page_height_mm * (rows - 1 - row) as f32).into_pt();
+                    for (index, x) in [(left, Pt(0.0)), (right, Mm(page_width_mm).into_pt())] {
+                        let page_ops = pages[index].take().unwrap_or_else(|| blank.clone());
+                        ops.extend(page_ops.into_iter().map(|op| offset_op(op, x, y)));
+                    }
+                }
+                sheets.push(PdfPage::new(Mm(sheet_width_mm), Mm(sheet_height_mm), ops));
+            }
+        }
+    }
+    book.pages = sheets;
+    book.page_width_mm = sheet_width_mm;
+    book.page_height_mm = sheet_height_mm;
+    book.fold_x_mm = Some(page_width_mm);
+}
+
+fn add_blank(page_width_mm: f32, page_height_mm: f32, bg_color: &Color) -> Vec<Op> {
+    let (width, height) = (Mm(page_width_mm).into_pt(), Mm(page_height_mm).into_pt());
+    vec![
+        Op::SetFillColor { col: bg_color.clone() },
+        Op::DrawPolygon {
+            polygon: Polygon {
+                rings: vec![PolygonRing {
+                    points: vec![
+                        point(Pt(0.0), Pt(0.0)),
+                        point(width, Pt(0.0)),
+                        point(width, height),
+                        point(Pt(0.0), height),
+                    ],
+                }],
+                mode: PaintMode::Fill,
+                winding_order: WindingOrder::NonZero,
+            },
+        },
+    ]
+}
+
+// 成品（裁切后）页面在印张上的边界
+struct Trim {
+    left: Pt,
+    bottom: Pt,
+    right: Pt,
+    top: Pt,
+}
+
+fn point(x: Pt, y: Pt) -> LinePoint {
+    LinePoint { p: Point { x, y }, bezier: false }
+}
+
+// 出血：成品边界外扩一圈底色，先于页面内容绘制
+fn add_bleed(trim: &Trim, bleed: Pt, bg_color: &Color, layer_id: &LayerInternalId) -> Vec<Op> {
+    vec![
+        Op::BeginLayer { layer_id: layer_id.clone() },
+        Op::SetFillColor { col: bg_color.clone() },
+        Op::DrawPolygon {
+            polygon: Polygon {
+                rings: vec![PolygonRing {
+                    points: vec![
+                        point(trim.left - bleed, trim.bottom - bleed),
+                        point(trim.right + bleed, trim.bottom - bleed),
+                        point(trim.right + bleed, trim.top + bleed),
+                        point(trim.left - bleed, trim.top + bleed),
+                    ],
+                }],
+                mode: PaintMode::Fill,
+                winding_order: WindingOrder::NonZero,
+            },
+        },
+        Op::EndLayer { layer_id: layer_id.clone() },
+    ]
+}
+
+// 裁切线在四角，折页线在中缝上下，套准标记在左右两边正中（无中缝时上下也加）
+fn add_marks(trim: &Trim, bleed: Pt, fold_x: Option<Pt>, layer_id: &LayerInternalId) -> Vec<Op> {
+    // 套准色：各色版都印，单色 RGB 输出时为黑
+    let registration = Color::Cmyk(Cmyk::new(1.0, 1.0, 1.0, 1.0, None));
+    let start = bleed + Mm(PRINT_MARK_GAP_MM).into_pt();
+    let end = start + Mm(PRINT_MARK_LENGTH_MM).into_pt();
+    let mut lines: Vec<[Point; 2]> = Vec::new();
+    for (x, sx) in [(trim.left, -1.0), (trim.right, 1.0)] {
+        for (y, sy) in [(trim.bottom, -1.0), (trim.top, 1.0)] {
+            lines.push([Point { x: x + start * sx, y }, Point { x: x + end * sx, y }]);
+            lines.push([Point { x, y: y + start * sy }, Point { x, y: y + end * sy }]);
+        }
+    }
+    if let Some(x) = fold_x {
+        lines.push([Point { x, y: trim.top + start }, Point { x, y: trim.top + end }]);
+        lines.push([Point { x, y: trim.bottom - start }, Point { x, y: trim.bottom - end }]);
+    }
+
+    let middle = (start + end) / 2.0;
+    let center_x = (trim.left + trim.right) / 2.0;
+    let center_y = (trim.bottom + trim.top) / 2.0;
+    let mut centers = vec![
+        (trim.left - middle, center_y),
+        (trim.right + middle, center_y),
+    ];
+    if fold_x.is_none() {
+        centers.push((center_x, trim.top + middle));
+        centers.push((center_x, trim.bottom - middle));
+    }
+    let radius = Mm(PRINT_MARK_LENGTH_MM).into_pt() * 0.3;
+    let arm = Mm(PRINT_MARK_LENGTH_MM).into_pt() * 0.5;
+    for &(x, y) in &centers {
+        lines.push([Point { x: x - arm, y }, Point { x: x + arm, y }]);
+        lines.push([Point { x, y: y - arm }, Point { x, y: y + arm }]);
+    }
+
+    let mut ops = vec![
+        Op::BeginLayer { layer_id: layer_id.clone() },
+        Op::SetOutlineColor { col: registration },
+        Op::SetOutlineThickness { pt: Pt(PRINT_MARK_WIDTH_PT) },
+    ];
+    ops.extend(lines.into_iter().map(|[a, b]| Op::DrawLine {
+        line: Line {
+            points: vec![point(a.x, a.y), point(b.x, b.y)],
+            is_closed: false,
+        },
+    }));
+    // 套准圆以多边形近似
+    for &(x, y) in &centers {
+        let points = (0..32)
+            .map(|i| {
+                let (sin, cos) = (i as f32 / 32.0 * std::f32::consts::TAU).sin_cos();
+                point(x + radius * cos, y + radius * sin)
+            })
+            .collect();
+        ops.push(Op::DrawLine { line: Line { points, is_closed: true } });
+    }
+    ops.push(Op::EndLayer { layer_id: layer_id.clone() });
+    ops
+}
+
+// 页面内容整体平移到印张上的成品位置
+fn offset_op(op: Op, dx: Pt, dy: Pt) -> Op {
+    let shift = |points: &mut Vec<LinePoint>| {
+        for point in points.iter_mut() {
+            point.p.x += dx;
+            point.p.y += dy;
+        }
+    };
+    match op {
+        Op::DrawLine { mut line } => {
+            shift(&mut line.points);
+            Op::DrawLine { line }
+        }
+        Op::DrawPolygon { mut polygon } => {
+            for ring in polygon.rings.iter_mut() {
+                shift(&mut ring.points);
+            }
+            Op::DrawPolygon { polygon }
+        }
+        Op::SetTextMatrix { matrix: TextMatrix::TranslateRotate(x, y, angle) } => Op::SetTextMatrix {
+            matrix: TextMatrix::TranslateRotate(x + dx, y + dy, angle),
+        },
+        Op::UseXobject { id, mut transform } => {
+            transform.translate_x = Some(transform.translate_x.unwrap_or(Pt(0.0)) + dx);
+            transform.translate_y = Some(transform.translate_y.unwrap_or(Pt(0.0)) + dy);
+            Op::UseXobject { id, transform }
+        }
+        op => op,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH_MM: f32 = 100.0;
+    const HEIGHT_MM: f32 = 150.0;
+
+    // 每页只有一条文字矩阵指令，旋转角记页码（从 0 起），平移量即页面在印张上的位置
+    fn sample_book(count: usize) -> PagedBook {
+        let pages = (0..count)
+            .map(|i| {
+                let ops = vec![Op::SetTextMatrix { matrix: TextMatrix::TranslateRotate(Pt(0.0), Pt(0.0), i as f32) }];
+                PdfPage::new(Mm(WIDTH_MM), Mm(HEIGHT_MM), ops)
+            })
+            .collect();
+        PagedBook {
+            doc: PdfDocument::new("test"),
+            name: "test".to_string(),
+            author: String::new(),
+            language: "zh-Hant",
+            pages,
+            page_width_mm: WIDTH_MM,
+            page_height_mm: HEIGHT_MM,
+            fonts: Vec::new(),
+            images: Vec::new(),
+            layers: Vec::new(),
+            fold_x_mm: None,
+            bg_color: Color::Rgb(Rgb::new(1.0, 1.0, 1.0, None)),
+            bg_layer: LayerInternalId("background".to_string()),
+        }
+    }
+
+    // 按绘制顺序列出一面上的各页：Some(页码)，空白页为 None
+    fn slots(sheet: &PdfPage) -> Vec<Option<usize>> {
+        sheet
+            .ops
+            .iter()
+            .filter_map(|op| match op {
+                Op::SetTextMatrix { matrix: TextMatrix::TranslateRotate(_, _, angle) } => Some(Some(*angle as usize)),
+                Op::DrawPolygon { .. } => Some(None),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // 一面上各页的位置（pt）
+    fn positions(sheet: &PdfPage) -> Vec<(f32, f32)> {
+        sheet
+            .ops
+            .iter()
+            .filter_map(|op| match op {
+                Op::SetTextMatrix { matrix: TextMatrix::TranslateRotate(x, y, _) } => Some((x.0, y.0)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn saddle_stitch_order() {
+        let mut book = sample_book(8);
+        impose_signatures(&mut book, None, 1);
+        let sides: Vec<_> = book.pages.iter().map(slots).collect();
+        assert_eq!(
+            sides,
+            vec![
+                vec![Some(0), Some(7)],
+                vec![Some(6), Some(1)],
+                vec![Some(2), Some(5)],
+                vec![Some(4), Some(3)],
+            ]
+        );
+        assert_eq!(positions(&book.pages[0]), vec![(0.0, 0.0), (Mm(WIDTH_MM).into_pt().0, 0.0)]);
+        assert_eq!(book.page_width_mm, WIDTH_MM * 2.0);
+        assert_eq!(book.page_height_mm, HEIGHT_MM);
+        assert_eq!(book.fold_x_mm, Some(WIDTH_MM));
+    }
+
+    #[test]
+    fn four_up_order() {
+        let mut book = sample_book(8);
+        impose_signatures(&mut book, Some(8), 2);
+        let sides: Vec<_> = book.pages.iter().map(slots).collect();
+        // 上排为外层对折纸，下排裁开后套入其内
+        assert_eq!(
+            sides,
+            vec![
+                vec![Some(0), Some(7), Some(2), Some(5)],
+                vec![Some(6), Some(1), Some(4), Some(3)],
+            ]
+        );
+        let (width, height) = (Mm(WIDTH_MM).into_pt().0, Mm(HEIGHT_MM).into_pt().0);
+        assert_eq!(positions(&book.pages[0]), vec![(0.0, height), (width, height), (0.0, 0.0), (width, 0.0)]);
+        assert_eq!(book.page_height_mm, HEIGHT_MM * 2.0);
+    }
+
+    #[test]
+    fn blank_padding() {
+        // 骑马订 5 页补足 8 页，空白页在帖末
+        let mut book = sample_book(5);
+        impose_signatures(&mut book, None, 1);
+        let sides: Vec<_> = book.pages.iter().map(slots).collect();
+        assert_eq!(
+            sides,
+            vec![
+                vec![Some(0), None],
+                vec![None, Some(1)],
+                vec![Some(2), None],
+                vec![Some(4), Some(3)],
+            ]
+        );
+        // 二拼每帖 4 页，第二帖只有 1 页
+        let mut book = sample_book(5);
+        impose_signatures(&mut book, Some(4), 1);
+        let sides: Vec<_> = book.pages.iter().map(slots).collect();
+        assert_eq!(
+            sides,
+            vec![
+                vec![Some(0), Some(3)],
+                vec![Some(2), Some(1)],
+                vec![Some(4), None],
+                vec![None, None],
+            ]
+        );
+    }
+}
@@ -1,8 +1,23 @@
 mod utils;
 mod pdfoption;
 mod config;
+mod woodblock;
+mod convert;
+mod numeral;
+mod metrics;
+mod ruby;
+mod document;
+mod epub;
+mod epubexport;
+mod raster;
+mod render;
+mod svg;
+mod imposition;
+mod pdfmeta;
 use std::path::{Path,PathBuf};
+use std::rc::Rc;
 use config::*;
+use slint::{ModelRc, SharedString, VecModel};
 slint::include_modules!();
 
 fn main() {    
@@ -12,6 +27,9 @@ fn main() {
     let dir_str = std::env::current_dir().unwrap().to_string_lossy().to_string();
     let _= ui.set_current_path(dir_str.clone().into());
     let ui_weak = ui.as_weak();
+    // 加载纸张预设
+    let paper_presets = Rc::new(config::load_paper_presets(PAPER_PRESETS_PATH));
+    set_paper_presets(&ui, &paper_presets);
     //创建PDF
     let ui_weak_pdf = ui_weak.clone();
     ui.on_create_pdf(move || {
@@ -21,6 +39,33 @@ fn main() {
         }
     });
 
+    let ui_weak_epub: slint::Weak<MainWindow> = ui_weak.clone();
+    // 生成EPUB
+    ui.on_create_epub(move || {
+        // 在闭包中使用弱引用访问UI
+        if let Some(ui) = ui_weak_epub.upgrade() { 
+            create_epub(&ui);
+        }
+    });
+
+    let ui_weak_png: slint::Weak<MainWindow> = ui_weak.clone();
+    // 导出PNG
+    ui.on_export_png(move || {
+        // 在闭包中使用弱引用访问UI
+        if let Some(ui) = ui_weak_png.upgrade() { 
+            export_png(&ui);
+        }
+    });
+
+    let ui_weak_svg: slint::Weak<MainWindow> = ui_weak.clone();
+    // 导出SVG
+    ui.on_export_svg(move || {
+        // 在闭包中使用弱引用访问UI
+        if let Some(ui) = ui_weak_svg.upgrade() { 
+            export_svg(&ui);
+        }
+    });
+
     let ui_weak_pdf_compress: slint::Weak<MainWindow> = ui_weak.clone();
     // 压缩PDF
     ui.on_pdf_compress_gs(move || {
@@ -55,12 +100,50 @@ fn main() {
             create_template(&ui);
         }
     }) ;
+    let ui_weak_suggest: slint::Weak<MainWindow> = ui_weak.clone();
+    // 推荐参数
+    ui.on_suggest(move || {
+        // 在闭包中使用弱引用访问UI
+        if let Some(ui) = ui_weak_suggest.upgrade() { 
+            suggest_pager(&ui);
+        }
+    }) ;
+    let ui_weak_calculate: slint::Weak<MainWindow> = ui_weak.clone();
+    let paper_presets_calculate = Rc::clone(&paper_presets);
+    // 选择纸张或排版方向
+    ui.on_calculate(move || {
+        // 在闭包中使用弱引用访问UI
+        if let Some(ui) = ui_weak_calculate.upgrade() { 
+            calculate_paper_size(&ui, &paper_presets_calculate);
+        }
+    }) ;
     ui.run().unwrap();
 }
 
+fn set_paper_presets(ui: &MainWindow, presets: &[PaperPreset]) {
+    let mut names: Vec<SharedString> = presets.iter().map(|p| p.name.clone().into()).collect();
+    names.push(PAPER_CUSTOM.into());
+    ui.set_canvas_sizes(ModelRc::from(Rc::new(VecModel::from(names))));
+}
+
+// 根据所选纸张和排版方向设置页面宽高，自定义尺寸只调整方向
+fn calculate_paper_size(ui: &MainWindow, presets: &[PaperPreset]) {
+    let (width_mm, height_mm) = match presets.iter().find(|p| p.name == ui.get_canvas_size().as_str()) {
+        Some(preset) => (preset.width_mm, preset.height_mm),
+        None => (
+            ui.get_page_width_mm().parse::<f32>().unwrap_or(0.0),
+            ui.get_page_height_mm().parse::<f32>().unwrap_or(0.0),
+        ),
+    };
+    let (short_mm, long_mm) = if width_mm < height_mm { (width_mm, height_mm) } else { (height_mm, width_mm) };
+    let (width_mm, height_mm) = if ui.get_canvas_direction() == "横向" { (long_mm, short_mm) } else { (short_mm, long_mm) };
+    ui.set_page_width_mm(width_mm.to_string().into());
+    ui.set_page_height_mm(height_mm.to_string().into());
+}
+
 fn create_template(ui: &MainWindow) {    
-    let (page,draw_color,file_info) = update(&ui); 
-    pdfoption::create_pdf_template(&page, &draw_color, &file_info);
+    let (page,draw_color,file_info,style,typeset) = update(&ui); 
+    pdfoption::create_pdf_template(&page, &draw_color, &file_info, &style, &typeset);
     println!("创建模板成功");
     let _ = ui.set_outtext_config("创建模板成功".to_string().into());
 }
@@ -83,12 +166,99 @@ fn create_pdf(ui: &MainWindow) {
         let _ = ui.set_outtext(format!("输入文件不存在: {}", ui.get_input_path().as_str()).into());
         return;
     }
-    let (page,draw_color,file_info) = update(&ui); 
-    pdfoption::create_pdf(&page, &draw_color, &file_info);
+    let (page,draw_color,file_info,style,typeset) = update(&ui); 
+    pdfoption::create_pdf(&page, &draw_color, &file_info, &style, &typeset);
     println!("创建{}成功", ui.get_output_path());
     let _ = ui.set_outtext(format!("创建{}成功", ui.get_output_path()).into());
 }
 
+fn create_epub(ui: &MainWindow) {
+    if !PathBuf::from(ui.get_input_path().as_str()).exists() {
+        println!("输入文件不存在: {}", ui.get_input_path().as_str());
+        let _ = ui.set_outtext(format!("输入文件不存在: {}", ui.get_input_path().as_str()).into());
+        return;
+    }
+    let (_page,_draw_color,file_info,_style,typeset) = update(&ui); 
+    match epubexport::create_epub(&file_info, &typeset) {
+        Ok((path, font_note)) => {
+            println!("创建{}成功，{}", path, font_note);
+            let _ = ui.set_outtext(format!("创建{}成功，{}", path, font_note).into());
+        }
+        Err(e) => {
+            println!("创建EPUB失败: {:?}", e);
+            let _ = ui.set_outtext(format!("创建EPUB失败: {:?}", e).into());
+        }
+    }
+}
+
+fn export_png(ui: &MainWindow) {
+    let font_path = PathBuf::from(ui.get_font_path().as_str());
+    let font_backup_path = PathBuf::from(ui.get_font_backup_path().as_str());
+    if !font_path.is_file() || !font_backup_path.is_file() {
+        println!("字体文件不存在: {}，或备份字体文件不存在: {}", font_path.display(), font_backup_path.display());
+        let _ = ui.set_outtext(format!("字体文件不存在: {}，或备份字体文件不存在: {}", font_path.display(), font_backup_path.display()).into());
+        return;
+    }
+    if !PathBuf::from(ui.get_input_path().as_str()).exists() {
+        println!("输入文件不存在: {}", ui.get_input_path().as_str());
+        let _ = ui.set_outtext(format!("输入文件不存在: {}", ui.get_input_path().as_str()).into());
+        return;
+    }
+    let (page,draw_color,file_info,style,typeset) = update(&ui); 
+    let Some(book) = pdfoption::layout_book(&page, &draw_color, &file_info, &style, &typeset) else {
+        let _ = ui.set_outtext(format!("读取输入文件失败: {}", ui.get_input_path().as_str()).into());
+        return;
+    };
+    // 图片与 PDF 输出到同一目录
+    let output_dir = Path::new(&file_info.outputpath).parent().map(Path::to_path_buf).unwrap_or_default();
+    match raster::export_png(&book, file_info.image_dpi, &output_dir) {
+        Ok(count) => {
+            println!("导出{}页PNG到{}", count, output_dir.display());
+            let _ = ui.set_outtext(format!("导出{}页PNG到{}", count, output_dir.display()).into());
+        }
+        Err(e) => {
+            println!("导出PNG失败: {:?}", e);
+            let _ = ui.set_outtext(format!("导出PNG失败: {:?}", e).into());
+        }
+    }
+}
+
+fn export_svg(ui: &MainWindow) {
+    let font_path = PathBuf::from(ui.get_font_path().as_str());
+    let font_backup_path = PathBuf::from(ui.get_font_backup_path().as_str());
+    if !font_path.is_file() || !font_backup_path.is_file() {
+        println!("字体文件不存在: {}，或备份字体文件不存在: {}", font_path.display(), font_backup_path.display());
+        let _ = ui.set_outtext(format!("字体文件不存在: {}，或备份字体文件不存在: {}", font_path.display(), font_backup_path.display()).into());
+        return;
+    }
+    if !PathBuf::from(ui.get_input_path().as_str()).exists() {
+        println!("输入文件不存在: {}", ui.get_input_path().as_str());
+        let _ = ui.set_outtext(format!("输入文件不存在: {}", ui.get_input_path().as_str()).into());
+        return;
+    }
+    let (page,draw_color,file_info,style,typeset) = update(&ui); 
+    let Some(book) = pdfoption::layout_book(&page, &draw_color, &file_info, &style, &typeset) else {
+        let _ = ui.set_outtext(format!("读取输入文件失败: {}", ui.get_input_path().as_str()).into());
+        return;
+    };
+    // 轮廓模式不依赖系统字体，文字模式可在编辑器中修改
+    let outline = match file_info.svg_text.as_str() {
+        "SVG轮廓" => true,
+        _ => false,
+    };
+    let output_dir = Path::new(&file_info.outputpath).parent().map(Path::to_path_buf).unwrap_or_default();
+    match svg::export_svg(&book, outline, &output_dir) {
+        Ok(count) => {
+            println!("导出{}页SVG到{}", count, output_dir.display());
+            let _ = ui.set_outtext(format!("导出{}页SVG到{}", count, output_dir.display()).into());
+        }
+        Err(e) => {
+            println!("导出SVG失败: {:?}", e);
+            let _ = ui.set_outtext(format!("导出SVG失败: {:?}", e).into());
+        }
+    }
+}
+
 fn pdf_compress_gs(ui: &MainWindow) {
     let pdf_path = ui.get_output_path();
             let compress_ratio = ui.get_compress_ratio() as u8;
@@ -121,9 +291,10 @@ fn get_config(ui: &MainWindow)  {
         let _ = ui.set_outtext(format!("配置文件不存在: {}", config_path.as_str()).into());
         return;
     }
-    let (page, draw_color, file_info) = load_config(config_path.as_str());  
+    let (page, draw_color, file_info, style, typeset) = load_config(config_path.as_str());  
     //combox.set_selected_index(base.font.main_index as u32);
-    let _ = update_ui(&ui,&page, &draw_color, &file_info);
+    let paper_presets = config::load_paper_presets(PAPER_PRESETS_PATH);
+    let _ = update_ui(&ui,&page, &draw_color, &file_info, &style, &typeset, &paper_presets);
     println!("加载的配置文件完成: {}", config_path);
     let _ = ui.set_outtext_config(format!("加载的配置文件完成: {}", config_path).into());
 }
@@ -158,35 +329,37 @@ fn save_config(ui: &MainWindow) {
     let _ = ui.set_outtext_config(format!("配置文件已保存: {}", config_path).into()); 
 }
 
-fn load_config(path: &str) -> (Pager,DrawColor,FileInfo) {
-    let (page,drcolor,fileinfo) = config::from_json_file (path);
-    (page,drcolor,fileinfo)
+fn load_config(path: &str) -> (Pager,DrawColor,FileInfo,Style,Typeset) {
+    let (page,drcolor,fileinfo,style,typeset) = config::from_json_file (path);
+    (page,drcolor,fileinfo,style,typeset)
 }
 
 fn save_config_file(ui: &MainWindow,path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let (page,draw_color,file_info) = update(&ui);
-    config::save_json(page,draw_color,file_info,path)?;
+    let (page,draw_color,file_info,style,typeset) = update(&ui);
+    config::save_json(page,draw_color,file_info,style,typeset,path)?;
     Ok(())
 }
 
 
-fn update(ui: &MainWindow) -> (Pager,DrawColor,FileInfo){
-    let page_width_mm= ui.get_page_width_mm().parse::<f32>().unwrap();
-    let page_height_mm =  ui.get_page_height_mm().parse::<f32>().unwrap();
-    let column_count = ui.get_column_count() as usize;
-    let center_width_mm = ui.get_center_width_mm() as f32;
-    let page_top_margin_mm = ui.get_page_top_margin_mm() as f32;
-    let page_left_margin_mm = ui.get_page_left_margin_mm() as f32;
-    let tail_margin_mm = ui.get_tail_margin_mm() as f32;   
-    let col_width_mm = (page_width_mm - center_width_mm - page_left_margin_mm * 2.0) 
-                           / column_count as f32;
-    
-    let page_bottom_margin_mm = page_left_margin_mm;
-    let page_right_margin_mm = page_left_margin_mm;   
-    let tail_long_offset_mm = tail_margin_mm * 0.4;
-    let tail_short_offset_mm = tail_margin_mm * 0.25;
-    let title_font_size_pt = (center_width_mm * 0.45 * MM_TO_PT).round() as f32;
-    let content_font_size_pt = (col_width_mm * 0.6 * MM_TO_PT).round() as f32;
+fn update(ui: &MainWindow) -> (Pager,DrawColor,FileInfo,Style,Typeset){
+    let page_width_mm= ui.get_page_width_mm().parse::<f32>().unwrap_or(297.0);
+    let page_height_mm =  ui.get_page_height_mm().parse::<f32>().unwrap_or(210.0);
+    let column_count = ui.get_column_count().max(1) as usize;
+    let center_width_mm = ui.get_center_width_mm().parse::<f32>().unwrap_or(20.0);
+    let page_top_margin_mm = ui.get_page_top_margin_mm().parse::<f32>().unwrap_or(20.0);
+    let page_bottom_margin_mm = ui.get_page_bottom_margin_mm().parse::<f32>().unwrap_or(8.0);
+    let page_left_margin_mm = ui.get_page_left_margin_mm().parse::<f32>().unwrap_or(8.0);
+    let page_right_margin_mm = ui.get_page_right_margin_mm().parse::<f32>().unwrap_or(8.0);
+    let tail_margin_mm = ui.get_tail_margin_mm().parse::<f32>().unwrap_or(30.0);
+    let tail_space_mm = ui.get_tail_space_mm().parse::<f32>().unwrap_or(LINE_SPACE_MM);
+    let tail_long_offset_mm = ui.get_tail_long_offset_mm().parse::<f32>().unwrap_or(12.0);
+    let tail_short_offset_mm = ui.get_tail_short_offset_mm().parse::<f32>().unwrap_or(7.5);
+    let line_width_pt = ui.get_line_width_pt().parse::<f32>().unwrap_or(LINE_WIDTH_PT);
+    let line_offset_pt = ui.get_line_offset_pt().parse::<f32>().unwrap_or(LINE_OFFSET_PT);
+    let title_font_size_pt = ui.get_title_font_size_pt().parse::<f32>().unwrap_or(24.0);
+    let content_font_size_pt = ui.get_content_font_size_pt().parse::<f32>().unwrap_or(18.0);
+    let layout_mode = ui.get_layout_mode().to_string();
+    let chars_per_column = ui.get_chars_per_column().max(1) as usize;
 
     let bg_color = ui.get_background_color().to_string();
     let draw_color = ui.get_font_color().to_string();
@@ -198,6 +371,47 @@ fn update(ui: &MainWindow) -> (Pager,DrawColor,FileInfo){
     let backup_font_path = ui.get_font_backup_path().to_string();
 
     let compressratio = ui.get_compress_ratio() as u8;
+    let image_dpi = (ui.get_image_dpi().max(0) as u32).clamp(IMAGE_DPI_MIN, IMAGE_DPI_MAX);
+    let svg_text = ui.get_svg_text().to_string();
+    let author = ui.get_author().trim().to_string();
+    let dynasty = ui.get_dynasty().trim().to_string();
+    let editor = ui.get_editor().trim().to_string();
+    let subject = ui.get_subject().trim().to_string();
+    let keywords = ui.get_keywords().trim().to_string();
+    let edition = ui.get_edition().trim().to_string();
+    let source = ui.get_source().trim().to_string();
+
+    let woodblock = ui.get_woodblock();
+    let woodblock_seed = ui.get_woodblock_seed().max(0) as u64;
+    let woodblock_strength = ui.get_woodblock_strength() / 100.0;
+    let frame_style = ui.get_frame_style().to_string();
+    let thin_line_width_pt = ui.get_thin_line_width_pt().parse::<f32>().unwrap_or(THIN_LINE_WIDTH_PT);
+    let tail_count = ui.get_tail_count().to_string();
+    let tail_fill = ui.get_tail_fill().to_string();
+    let tail_direction = ui.get_tail_direction().to_string();
+    let xiangbi = ui.get_xiangbi().to_string();
+    let xiangbi_width_pt = ui.get_xiangbi_width_pt().parse::<f32>().unwrap_or(LINE_WIDTH_PT);
+    let column_rules = ui.get_column_rules();
+    let ear = ui.get_ear();
+    let ear_position = ui.get_ear_position().to_string();
+    let ear_width_mm = ui.get_ear_width_mm().parse::<f32>().unwrap_or(5.0);
+    let ear_height_mm = ui.get_ear_height_mm().parse::<f32>().unwrap_or(24.0);
+    let ear_source = ui.get_ear_source().to_string();
+    let ear_text = ui.get_ear_text().to_string();
+    let print_mode = ui.get_print_mode().to_string();
+    let bleed_mm = ui.get_bleed_mm().parse::<f32>().unwrap_or(BLEED_MM);
+    let imposition = ui.get_imposition().to_string();
+
+    let font_offset_scale = config::positive_scale(ui.get_font_offset_scale().parse::<f32>().unwrap_or(FONT_OFFSET_SCALE), FONT_OFFSET_SCALE);
+    let pun_scale = config::positive_scale(ui.get_pun_scale().parse::<f32>().unwrap_or(PUN_SCALE), PUN_SCALE);
+    let pun_pub = ui.get_pun_pub().parse::<f32>().unwrap_or(PUN_PUB);
+    let pagin_scale = config::positive_scale(ui.get_pagin_scale().parse::<f32>().unwrap_or(PAGIN_SCALE), PAGIN_SCALE);
+    let punctuation_mode = ui.get_punctuation_mode().to_string();
+    let kinsoku_mode = ui.get_kinsoku_mode().to_string();
+    let script_convert = ui.get_script_convert().to_string();
+    let number_mode = ui.get_number_mode().to_string();
+    let convert_table_path = ui.get_convert_table_path().to_string();
+    let substitutions = config::substitutions_from_text(ui.get_substitutions().as_str());
 
     let page = Pager {
         page_width_mm:  page_width_mm,
@@ -209,13 +423,15 @@ fn update(ui: &MainWindow) -> (Pager,DrawColor,FileInfo){
         page_right_margin_mm: page_right_margin_mm,  
         center_width_mm:center_width_mm,     
         tail_margin_mm: tail_margin_mm,
-        tail_space_mm: LINE_SPACE_MM,
+        tail_space_mm: tail_space_mm,
         tail_long_offset_mm: tail_long_offset_mm,
         tail_short_offset_mm: tail_short_offset_mm,
-        line_width_pt: LINE_WIDTH_PT,
-        line_offset_pt: LINE_OFFSET_PT,
+        line_width_pt: line_width_pt,
+        line_offset_pt: line_offset_pt,
         title_font_size_pt: title_font_size_pt,
         content_font_size_pt: content_font_size_pt,
+        layout_mode: layout_mode,
+        chars_per_column: chars_per_column,
     };
     
     let draw_color = DrawColor{
@@ -228,22 +444,61 @@ fn update(ui: &MainWindow) -> (Pager,DrawColor,FileInfo){
         name:bookname.clone(),
         inputpath:bookinputpath,
         outputpath:bookoutputpath,
-        author:bookname.clone(),
-        creater:bookname.clone(),
+        author:author,
+        // 图层创建人取整理者
+        creater:editor.clone(),
+        dynasty:dynasty,
+        editor:editor,
+        subject:subject,
+        keywords:keywords,
+        edition:edition,
+        source:source,
         main_path:main_font_path,
         backup_path:backup_font_path,
         compressratio:compressratio,
+        image_dpi:image_dpi,
+        svg_text:svg_text,
     };
-    (page,draw_color,fileinfo)
+
+    let style = Style{
+        woodblock:woodblock,
+        woodblock_seed:woodblock_seed,
+        woodblock_strength:woodblock_strength,
+        frame_style:frame_style,
+        thin_line_width_pt:thin_line_width_pt,
+        tail_count:tail_count,
+        tail_fill:tail_fill,
+        tail_direction:tail_direction,
+        xiangbi:xiangbi,
+        xiangbi_width_pt:xiangbi_width_pt,
+        column_rules:column_rules,
+        ear:ear,
+        ear_position:ear_position,
+        ear_width_mm:ear_width_mm,
+        ear_height_mm:ear_height_mm,
+        ear_source:ear_source,
+        ear_text:ear_text,
+        print_mode:print_mode,
+        bleed_mm:bleed_mm,
+        imposition:imposition,
+    };
+
+    let typeset = Typeset{
+        font_offset_scale:font_offset_scale,
+        pun_scale:pun_scale,
+        pun_pub:pun_pub,
+        pagin_scale:pagin_scale,
+        punctuation_mode:punctuation_mode,
+        kinsoku_mode:kinsoku_mode,
+        script_convert:script_convert,
+        number_mode:number_mode,
+        convert_table_path:convert_table_path,
+        substitutions:substitutions,
+    };
+    (page,draw_color,fileinfo,style,typeset)
 }
-fn update_ui(ui: &MainWindow,page: &Pager, drawcolor: &DrawColor,fileinfo: &FileInfo) {
-    ui.set_page_width_mm(page.page_width_mm.to_string().into());
-    ui.set_page_height_mm(page.page_height_mm.to_string().into());
-    ui.set_center_width_mm((page.center_width_mm as i32).into());
-    ui.set_page_top_margin_mm((page.page_top_margin_mm as i32).into());
-    ui.set_page_left_margin_mm((page.page_left_margin_mm as i32).into());
-    ui.set_tail_margin_mm((page.tail_margin_mm as i32).into());
-    ui.set_column_count((page.column_count as i32).into());
+fn update_ui(ui: &MainWindow,page: &Pager, drawcolor: &DrawColor,fileinfo: &FileInfo,style: &Style,typeset: &Typeset,paper_presets: &[PaperPreset]) {
+    update_ui_pager(&ui, page);
     ui.set_font_color(drawcolor.draw.clone().into());
     ui.set_background_color(drawcolor.bg.clone().into());
     ui.set_line_color(drawcolor.line.clone().into());
@@ -253,26 +508,78 @@ fn update_ui(ui: &MainWindow,page: &Pager, drawcolor: &DrawColor,fileinfo: &File
     ui.set_output_path(fileinfo.outputpath.to_string().into());
     ui.set_book_name(fileinfo.name.to_string().into());
     ui.set_compress_ratio(fileinfo.compressratio.into());
+    ui.set_image_dpi(fileinfo.image_dpi as i32);
+    ui.set_svg_text(fileinfo.svg_text.to_string().into());
+    ui.set_author(fileinfo.author.to_string().into());
+    ui.set_dynasty(fileinfo.dynasty.to_string().into());
+    ui.set_editor(fileinfo.editor.to_string().into());
+    ui.set_subject(fileinfo.subject.to_string().into());
+    ui.set_keywords(fileinfo.keywords.to_string().into());
+    ui.set_edition(fileinfo.edition.to_string().into());
+    ui.set_source(fileinfo.source.to_string().into());
+    ui.set_woodblock(style.woodblock);
+    ui.set_woodblock_seed(style.woodblock_seed as i32);
+    ui.set_woodblock_strength((style.woodblock_strength * 100.0).round());
+    ui.set_frame_style(style.frame_style.clone().into());
+    ui.set_thin_line_width_pt(style.thin_line_width_pt.to_string().into());
+    ui.set_tail_count(style.tail_count.clone().into());
+    ui.set_tail_fill(style.tail_fill.clone().into());
+    ui.set_tail_direction(style.tail_direction.clone().into());
+    ui.set_xiangbi(style.xiangbi.clone().into());
+    ui.set_xiangbi_width_pt(style.xiangbi_width_pt.to_string().into());
+    ui.set_column_rules(style.column_rules);
+    ui.set_ear(style.ear);
+    ui.set_ear_position(style.ear_position.clone().into());
+    ui.set_ear_width_mm(style.ear_width_mm.to_string().into());
+    ui.set_ear_height_mm(style.ear_height_mm.to_string().into());
+    ui.set_ear_source(style.ear_source.clone().into());
+    ui.set_ear_text(style.ear_text.clone().into());
+    ui.set_print_mode(style.print_mode.clone().into());
+    ui.set_bleed_mm(style.bleed_mm.to_string().into());
+    ui.set_imposition(style.imposition.clone().into());
+    ui.set_font_offset_scale(typeset.font_offset_scale.to_string().into());
+    ui.set_pun_scale(typeset.pun_scale.to_string().into());
+    ui.set_pun_pub(typeset.pun_pub.to_string().into());
+    ui.set_pagin_scale(typeset.pagin_scale.to_string().into());
+    ui.set_punctuation_mode(typeset.punctuation_mode.clone().into());
+    ui.set_kinsoku_mode(typeset.kinsoku_mode.clone().into());
+    ui.set_script_convert(typeset.script_convert.clone().into());
+    ui.set_number_mode(typeset.number_mode.clone().into());
+    ui.set_convert_table_path(typeset.convert_table_path.clone().into());
+    ui.set_substitutions(config::substitutions_to_text(&typeset.substitutions).into());
     let direction = if page.page_width_mm > page.page_height_mm {"横向"}else{"纵向"};
     ui.set_canvas_direction(direction.into());
+    set_paper_presets(&ui, paper_presets);
+    let paper_size = config::find_paper_preset(paper_presets, page.page_width_mm, page.page_height_mm);
+    ui.set_canvas_size(paper_size.into());
+}
 
-    // 主逻辑
-    if direction == "横向" {
-        let paper_size = get_paper_size(page.page_width_mm);
-        ui.set_canvas_size(paper_size.into());
-    } else {
-        let paper_size = get_paper_size(page.page_height_mm);
-        ui.set_canvas_size(paper_size.into());
-    }  
+// 页面参数全部按原值回填，不做推算
+fn update_ui_pager(ui: &MainWindow,page: &Pager) {
+    ui.set_page_width_mm(page.page_width_mm.to_string().into());
+    ui.set_page_height_mm(page.page_height_mm.to_string().into());
+    ui.set_column_count(page.column_count as i32);
+    ui.set_center_width_mm(page.center_width_mm.to_string().into());
+    ui.set_page_top_margin_mm(page.page_top_margin_mm.to_string().into());
+    ui.set_page_bottom_margin_mm(page.page_bottom_margin_mm.to_string().into());
+    ui.set_page_left_margin_mm(page.page_left_margin_mm.to_string().into());
+    ui.set_page_right_margin_mm(page.page_right_margin_mm.to_string().into());
+    ui.set_tail_margin_mm(page.tail_margin_mm.to_string().into());
+    ui.set_tail_space_mm(page.tail_space_mm.to_string().into());
+    ui.set_tail_long_offset_mm(page.tail_long_offset_mm.to_string().into());
+    ui.set_tail_short_offset_mm(page.tail_short_offset_mm.to_string().into());
+    ui.set_line_width_pt(page.line_width_pt.to_string().into());
+    ui.set_line_offset_pt(page.line_offset_pt.to_string().into());
+    ui.set_title_font_size_pt(page.title_font_size_pt.to_string().into());
+    ui.set_content_font_size_pt(page.content_font_size_pt.to_string().into());
+    ui.set_layout_mode(page.layout_mode.clone().into());
+    ui.set_chars_per_column(page.chars_per_column as i32);
 }
 
-// 提取重复的匹配逻辑为函数
-fn get_paper_size(mm: f32) -> &'static str {
-    match mm {
-        297.0 => "A4",
-        250.0 => "B5",
-        260.0 => "16K",
-        184.0 => "32K",
-        _ => "A4", // 默认A4
-    }
-}
\ No newline at end of file
+// 按当前页面尺寸推算鱼尾偏移和字体大小，仅作为推荐值回填界面
+fn suggest_pager(ui: &MainWindow) {
+    let (page, _, _, _, _) = update(&ui);
+    let page = config::suggest_pager(&page);
+    update_ui_pager(&ui, &page);
+    let _ = ui.set_outtext_config("已填入推荐参数".to_string().into());
+}
@@ -0,0 +1,491 @@
+use std::collections::{HashMap, HashSet};
+use ab_glyph::{Font, FontVec, GlyphId};
+
+// 字体解析失败时拉丁字母按半个字宽估算
+const FALLBACK_ADVANCE_EM: f32 = 0.5;
+const FALLBACK_DESCENT_EM: f32 = 0.12;
+
+/// 字体度量：用于测量横排拉丁文字的长度、查找竖排字形，与 PDF 嵌入的字体为同一文件
+pub struct FontMetrics {
+    font: Option<FontVec>,
+    vertical: HashMap<u16, u16>,  // GSUB vert/vrt2 竖排替换：横排字形 -> 竖排字形
+    vmtx: Vec<(u16, i16)>,        // 竖排度量：每个字形的纵向前进量和顶部留白
+}
+
+/// 竖排符号的画法
+pub enum VerticalGlyph {
+    Substituted(u16),  // 字体自带的竖排字形（GSUB vert/vrt2）
+    Presentation(char), // Unicode 竖排标点（U+FE10–FE4F）
+    Rotate,            // 字体两者皆无，旋转横排字形
+}
+
+impl FontMetrics {
+    /// index 为字体集合中的序号，解析失败时再按单一字体解析
+    pub fn from_bytes(bytes: &[u8], index: u32) -> Self {
+        let font = FontVec::try_from_vec_and_index(bytes.to_vec(), index)
+            .or_else(|_| FontVec::try_from_vec(bytes.to_vec()))
+            .map_err(|e| eprintln!("⚠️ 字体度量读取失败：{}，拉丁文字按半字宽估算", e))
+            .ok();
+        let tables = font_tables(bytes, index).unwrap_or_default();
+        let vertical = tables
+            .get(b"GSUB")
+            .map(|gsub| vertical_substitutions(gsub))
+            .unwrap_or_default();
+        let vmtx = match (tables.get(b"vhea"), tables.get(b"vmtx")) {
+            (Some(vhea), Some(vmtx)) => vertical_metrics(vhea, vmtx),
+            _ => Vec::new(),
+        };
+        FontMetrics { font, vertical, vmtx }
+    }
+
+    fn units_per_em(font: &FontVec) -> f32 {
+        font.units_per_em().unwrap_or(1000.0)
+    }
+
+    /// 横排文字的宽度，以字号为单位（em），含字偶距调整
+    pub fn text_width_em(&self, text: &str) -> f32 {
+        let Some(font) = &self.font else {
+            return text.chars().count() as f32 * FALLBACK_ADVANCE_EM;
+        };
+        let mut width = 0.0;
+        let mut previous: Option<GlyphId> = None;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(prev) = previous {
+                width += font.kern_unscaled(prev, id);
+            }
+            width += font.h_advance_unscaled(id);
+            previous = Some(id);
+        }
+        width / Self::units_per_em(font)
+    }
+
+    /// 基线以下的深度，以字号为单位（em），取正值
+    pub fn descent_em(&self) -> f32 {
+        match &self.font {
+            Some(font) => -font.descent_unscaled() / Self::units_per_em(font),
+            None => FALLBACK_DESCENT_EM,
+        }
+    }
+
+    /// 旋转排列的拉丁文字在竖行中占用的字位数，至少一个
+    pub fn cells(&self, text: &str, font_size: f32, cell_pt: f32) -> usize {
+        let length = self.text_width_em(text) * font_size;
+        ((length / cell_pt).ceil() as usize).max(1)
+    }
+
+    /// 字形外框中心，以字号为单位（em），y 向上；空白字形按前进宽度的一半、基线计
+    pub fn glyph_center_em(&self, glyph_id: u16) -> (f32, f32) {
+        let Some(font) = &self.font else {
+            return (FALLBACK_ADVANCE_EM, 0.0);
+        };
+        let units = Self::units_per_em(font);
+        let id = GlyphId(glyph_id);
+        match font.outline(id) {
+            Some(outline) => (
+                (outline.bounds.min.x + outline.bounds.max.x) / 2.0 / units,
+                (outline.bounds.min.y + outline.bounds.max.y) / 2.0 / units,
+            ),
+            None => (font.h_advance_unscaled(id) / 2.0 / units, 0.0),
+        }
+    }
+
+    pub fn char_center_em(&self, c: char) -> (f32, f32) {
+        match &self.font {
+            Some(font) => self.glyph_center_em(font.glyph_id(c).0),
+            None => (FALLBACK_ADVANCE_EM, 0.0),
+        }
+    }
+
+    /// 使字形外框在一个字宽内水平居中所需的右移量，以字号为单位（em）
+    pub fn center_offset_em(&self, c: char) -> f32 {
+        0.5 - self.char_center_em(c).0
+    }
+
+    /// 竖排符号依次取字体的竖排字形、Unicode 竖排标点，都没有时旋转
+    pub fn vertical_glyph(&self, c: char) -> VerticalGlyph {
+        let Some(font) = &self.font else {
+            return VerticalGlyph::Rotate;
+        };
+        let id = font.glyph_id(c);
+        if let Some(vertical_id) = self.vertical.get(&id.0) {
+            return VerticalGlyph::Substituted(*vertical_id);
+        }
+        match presentation_form(c) {
+            Some(form) if font.glyph_id(form).0 != 0 => VerticalGlyph::Presentation(form),
+            _ => VerticalGlyph::Rotate,
+        }
+    }
+
+    /// 竖排字形相对横排基线的下移量，以字号为单位（em）
+    /// 按 vmtx 的顶部留白求出字形顶端，使其与横排字身顶端对齐；纵向前进量不足一字时在字位内居中
+    pub fn vertical_shift_em(&self, glyph_id: u16) -> f32 {
+        let (Some(font), Some(&(advance, top_bearing))) = (&self.font, self.vmtx.get(glyph_id as usize)) else {
+            return 0.0;
+        };
+        let Some(outline) = font.outline(GlyphId(glyph_id)) else {
+            return 0.0;
+        };
+        let units = Self::units_per_em(font);
+        let origin_y = top_bearing as f32 + outline.bounds.max.y;
+        let centering = (units - advance as f32).max(0.0) / 2.0;
+        (origin_y - font.ascent_unscaled() + centering) / units
+    }
+}
+
+/// 字体的家族名（name 表），优先取 Windows 平台英文名，用于 SVG 等按名称引用字体的场合
+pub fn font_family_name(bytes: &[u8], index: u32) -> Option<String> {
+    let tables = font_tables(bytes, index)?;
+    let name = tables.get(b"name")?;
+    let count = read_u16(name, 2)? as usize;
+    let storage = read_u16(name, 4)? as usize;
+    let mut best: Option<(u32, String)> = None;
+    for i in 0..count {
+        let record = 6 + 12 * i;
+        let (Some(platform), Some(language), Some(name_id), Some(length), Some(offset)) = (
+            read_u16(name, record),
+            read_u16(name, record + 4),
+            read_u16(name, record + 6),
+            read_u16(name, record + 8),
+            read_u16(name, record + 10),
+        ) else {
+            break;
+        };
+        // 16 为排版家族名，1 为家族名
+        if name_id != 1 && name_id != 16 {
+            continue;
+        }
+        let Some(raw) = name.get(storage + offset as usize..storage + offset as usize + length as usize) else {
+            continue;
+        };
+        let text = match platform {
+            0 | 3 => String::from_utf16_lossy(&raw.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect::<Vec<u16>>()),
+            1 => raw.iter().map(|b| *b as char).collect(),
+            _ => continue,
+        };
+        // 排序：排版家族名优先，其次 Windows 英文、Windows 其他语言、其他平台
+        let rank = if name_id == 16 { 0 } else { 4 }
+            + match (platform, language) {
+                (3, 0x0409) => 0,
+                (3, _) => 1,
+                _ => 2,
+            };
+        if !text.trim().is_empty() && best.as_ref().is_none_or(|(r, _)| rank < *r) {
+            best = Some((rank, text.trim().to_string()));
+        }
+    }
+    best.map(|(_, text)| text)
+}
+
+/// 按用字精简字体，供 EPUB 嵌入：只保留 text 中各字的字形、竖排替换字形和组合字形的部件，
+/// 其余字形的轮廓清空，字形编号和 cmap、GSUB 等表不变；字体集合（ttc）取出 index 所指的字体。
+/// 只能精简 TrueType 轮廓（glyf），CFF 轮廓的字体返回 None
+pub fn subset_font(bytes: &[u8], index: u32, text: &str) -> Option<Vec<u8>> {
+    let tables = font_tables(bytes, index)?;
+    let (glyf, loca, head, maxp) = (tables.get(b"glyf")?, tables.get(b"loca")?, tables.get(b"head")?, tables.get(b"maxp")?);
+    let glyph_count = read_u16(maxp, 4)? as usize;
+    let long_loca = read_u16(head, 50)? == 1;
+    let offsets: Vec<usize> = (0..=glyph_count)
+        .map(|i| match long_loca {
+            true => read_u32(loca, 4 * i).map(|offset| offset as usize),
+            false => read_u16(loca, 2 * i).map(|offset| offset as usize * 2),
+        })
+        .collect::<Option<_>>()?;
+    let glyph_data = |id: u16| glyf.get(*offsets.get(id as usize)?..*offsets.get(id as usize + 1)?);
+
+    let metrics = FontMetrics::from_bytes(bytes, index);
+    let font = metrics.font.as_ref()?;
+    let mut used: HashSet<u16> = HashSet::from([0]);
+    for c in text.chars() {
+        let id = font.glyph_id(c).0;
+        used.insert(id);
+        // 阅读器竖排时按 GSUB vert 取竖排字形，或改用 Unicode 竖排标点
+        if let Some(vertical) = metrics.vertical.get(&id) {
+            used.insert(*vertical);
+        }
+        if let Some(form) = presentation_form(c) {
+            used.insert(font.glyph_id(form).0);
+        }
+    }
+    let mut pending: Vec<u16> = used.iter().copied().collect();
+    while let Some(id) = pending.pop() {
+        for component in glyph_components(glyph_data(id).unwrap_or_default()) {
+            if used.insert(component) {
+                pending.push(component);
+            }
+        }
+    }
+
+    // 重建 glyf，loca 一律用长格式
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity(4 * (glyph_count + 1));
+    for id in 0..glyph_count {
+        new_loca.extend((new_glyf.len() as u32).to_be_bytes());
+        if used.contains(&(id as u16)) {
+            new_glyf.extend(glyph_data(id as u16)?);
+            new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+        }
+    }
+    new_loca.extend((new_glyf.len() as u32).to_be_bytes());
+    let mut new_head = head.to_vec();
+    new_head.get_mut(8..12)?.fill(0);
+    new_head.get_mut(50..52)?.copy_from_slice(&1u16.to_be_bytes());
+
+    // 数字签名在改动后失效，不保留
+    let mut entries: Vec<([u8; 4], &[u8])> = tables
+        .iter()
+        .filter(|(tag, _)| !matches!(*tag, b"DSIG" | b"glyf" | b"loca" | b"head"))
+        .map(|(tag, table)| (*tag, *table))
+        .collect();
+    entries.extend([(*b"glyf", &new_glyf[..]), (*b"loca", &new_loca[..]), (*b"head", &new_head[..])]);
+    entries.sort_by_key(|(tag, _)| *tag);
+    Some(write_sfnt(&entries))
+}
+
+// 组合字形引用的部件字形
+fn glyph_components(data: &[u8]) -> Vec<u16> {
+    let mut components = Vec::new();
+    if read_u16(data, 0).is_none_or(|contours| (contours as i16) >= 0) {
+        return components;
+    }
+    let mut offset = 10;
+    while let (Some(flags), Some(glyph)) = (read_u16(data, offset), read_u16(data, offset + 2)) {
+        components.push(glyph);
+        // 参数为字或字节，其后依次可能是统一缩放、x/y 缩放或 2×2 矩阵
+        offset += 4 + if flags & 0x0001 != 0 { 4 } else { 2 };
+        offset += if flags & 0x0008 != 0 {
+            2
+        } else if flags & 0x0040 != 0 {
+            4
+        } else if flags & 0x0080 != 0 {
+            8
+        } else {
+            0
+        };
+        if flags & 0x0020 == 0 {
+            break;
+        }
+    }
+    components
+}
+
+// 按表目录写出 TrueType 字体文件，表按标签排序，各表四字节对齐，并重算校验和
+fn write_sfnt(tables: &[([u8; 4], &[u8])]) -> Vec<u8> {
+    let checksum = |data: &[u8]| {
+        data.chunks(4).fold(0u32, |sum, chunk| {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            sum.wrapping_add(u32::from_be_bytes(word))
+        })
+    };
+    let count = tables.len() as u16;
+    let entry_selector = 15 - count.max(1).leading_zeros() as u16;
+    let search_range = 16u16 << entry_selector;
+    let mut font = Vec::new();
+    font.extend(0x0001_0000u32.to_be_bytes());
+    for value in [count, search_range, entry_selector, count * 16 - search_range] {
+        font.extend(value.to_be_bytes());
+    }
+    let mut offset = 12 + 16 * tables.len();
+    let mut head_offset = None;
+    for (tag, data) in tables {
+        if tag == b"head" {
+            head_offset = Some(offset);
+        }
+        font.extend(tag);
+        font.extend(checksum(data).to_be_bytes());
+        font.extend((offset as u32).to_be_bytes());
+        font.extend((data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in tables {
+        font.extend(*data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+    // head 的 checkSumAdjustment 使整个文件的校验和为 0xB1B0AFBA
+    if let Some(head) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+        font[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    font
+}
+
+// 读取字体表目录，字体集合（ttc）按序号取其中一个字体
+fn font_tables(data: &[u8], index: u32) -> Option<HashMap<[u8; 4], &[u8]>> {
+    let face = if data.get(0..4)? == b"ttcf" {
+        let count = read_u32(data, 8)?;
+        let index = if index < count { index } else { 0 };
+        read_u32(data, 12 + 4 * index as usize)? as usize
+    } else {
+        0
+    };
+    let count = read_u16(data, face + 4)? as usize;
+    let mut tables = HashMap::new();
+    for i in 0..count {
+        let record = face + 12 + 16 * i;
+        let tag: [u8; 4] = data.get(record..record + 4)?.try_into().ok()?;
+        let offset = read_u32(data, record + 8)? as usize;
+        let length = read_u32(data, record + 12)? as usize;
+        if let Some(table) = data.get(offset..offset + length) {
+            tables.insert(tag, table);
+        }
+    }
+    Some(tables)
+}
+
+// 从 GSUB 表取 vert、vrt2 特性的单字替换，vrt2 覆盖 vert
+fn vertical_substitutions(gsub: &[u8]) -> HashMap<u16, u16> {
+    let mut substitutions = HashMap::new();
+    let (Some(feature_list), Some(lookup_list)) = (read_u16(gsub, 6), read_u16(gsub, 8)) else {
+        return substitutions;
+    };
+    let (feature_list, lookup_list) = (feature_list as usize, lookup_list as usize);
+    for wanted in [b"vert", b"vrt2"] {
+        let feature_count = read_u16(gsub, feature_list).unwrap_or(0) as usize;
+        for i in 0..feature_count {
+            let record = feature_list + 2 + 6 * i;
+            if gsub.get(record..record + 4) != Some(&wanted[..]) {
+                continue;
+            }
+            let Some(feature) = read_u16(gsub, record + 4).map(|offset| feature_list + offset as usize) else {
+                continue;
+            };
+            let lookup_count = read_u16(gsub, feature + 2).unwrap_or(0) as usize;
+            for j in 0..lookup_count {
+                let Some(lookup_index) = read_u16(gsub, feature + 4 + 2 * j) else {
+                    continue;
+                };
+                let Some(lookup) = read_u16(gsub, lookup_list + 2 + 2 * lookup_index as usize) else {
+                    continue;
+                };
+                read_lookup(gsub, lookup_list + lookup as usize, &mut substitutions);
+            }
+        }
+    }
+    substitutions
+}
+
+// 读取一个查找表，只处理单字替换（类型 1）及其扩展（类型 7）
+fn read_lookup(gsub: &[u8], lookup: usize, substitutions: &mut HashMap<u16, u16>) {
+    let lookup_type = read_u16(gsub, lookup).unwrap_or(0);
+    let subtable_count = read_u16(gsub, lookup + 4).unwrap_or(0) as usize;
+    for i in 0..subtable_count {
+        let Some(subtable) = read_u16(gsub, lookup + 6 + 2 * i).map(|offset| lookup + offset as usize) else {
+            continue;
+        };
+        match lookup_type {
+            1 => read_single_substitution(gsub, subtable, substitutions),
+            7 if read_u16(gsub, subtable + 2) == Some(1) => {
+                if let Some(offset) = read_u32(gsub, subtable + 4) {
+                    read_single_substitution(gsub, subtable + offset as usize, substitutions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn read_single_substitution(gsub: &[u8], subtable: usize, substitutions: &mut HashMap<u16, u16>) {
+    let Some(coverage) = read_u16(gsub, subtable + 2).map(|offset| subtable + offset as usize) else {
+        return;
+    };
+    let glyphs = read_coverage(gsub, coverage);
+    match read_u16(gsub, subtable) {
+        Some(1) => {
+            // 格式 1：统一加上字形编号差
+            let delta = read_u16(gsub, subtable + 4).unwrap_or(0);
+            for glyph in glyphs {
+                substitutions.insert(glyph, glyph.wrapping_add(delta));
+            }
+        }
+        Some(2) => {
+            // 格式 2：按覆盖表顺序逐个给出替换字形
+            for (i, glyph) in glyphs.into_iter().enumerate() {
+                if let Some(substitute) = read_u16(gsub, subtable + 6 + 2 * i) {
+                    substitutions.insert(glyph, substitute);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// 覆盖表按覆盖序号顺序列出字形
+fn read_coverage(data: &[u8], coverage: usize) -> Vec<u16> {
+    let count = read_u16(data, coverage + 2).unwrap_or(0) as usize;
+    match read_u16(data, coverage) {
+        Some(1) => (0..count).filter_map(|i| read_u16(data, coverage + 4 + 2 * i)).collect(),
+        Some(2) => (0..count)
+            .filter_map(|i| {
+                let range = coverage + 4 + 6 * i;
+                Some(read_u16(data, range)?..=read_u16(data, range + 2)?)
+            })
+            .flatten()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// vmtx：前 numOfLongVerMetrics 个字形各有前进量和留白，其后的字形沿用最后一个前进量
+fn vertical_metrics(vhea: &[u8], vmtx: &[u8]) -> Vec<(u16, i16)> {
+    let long_count = read_u16(vhea, 34).unwrap_or(0) as usize;
+    let mut metrics: Vec<(u16, i16)> = (0..long_count)
+        .map_while(|i| Some((read_u16(vmtx, 4 * i)?, read_u16(vmtx, 4 * i + 2)? as i16)))
+        .collect();
+    if let Some(&(last_advance, _)) = metrics.last() {
+        let mut offset = 4 * long_count;
+        while let Some(bearing) = read_u16(vmtx, offset) {
+            metrics.push((last_advance, bearing as i16));
+            offset += 2;
+        }
+    }
+    metrics
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+// Unicode 竖排标点（U+FE10–FE4F）
+fn presentation_form(c: char) -> Option<char> {
+    let form = match c {
+        '，' => '︐',
+        '、' => '︑',
+        '。' => '︒',
+        '：' => '︓',
+        '；' => '︔',
+        '！' => '︕',
+        '？' => '︖',
+        '〖' => '︗',
+        '〗' => '︘',
+        '…' => '︙',
+        '‥' => '︰',
+        '—' => '︱',
+        '–' | '-' => '︲',
+        '＿' => '︳',
+        '（' | '(' => '︵',
+        '）' | ')' => '︶',
+        '｛' | '{' => '︷',
+        '｝' | '}' => '︸',
+        '〔' => '︹',
+        '〕' => '︺',
+        '【' => '︻',
+        '】' => '︼',
+        '《' => '︽',
+        '》' => '︾',
+        '〈' | '<' => '︿',
+        '〉' | '>' => '﹀',
+        '「' => '﹁',
+        '」' => '﹂',
+        '『' => '﹃',
+        '』' => '﹄',
+        '［' | '[' => '﹇',
+        '］' | ']' => '﹈',
+        _ => return None,
+    };
+    Some(form)
+}
@@ -0,0 +1,184 @@
+// 阿拉伯数字转汉字：按上下文区分基数（十二）和逐字（一九四九）读法
+use crate::ruby;
+
+const DIGITS: [&str; 10] = ["〇", "一", "二", "三", "四", "五", "六", "七", "八", "九"];
+const CARDINAL_DIGITS: [&str; 10] = ["零", "一", "二", "三", "四", "五", "六", "七", "八", "九"];
+const CARDINAL_UNITS: [&str; 4] = ["", "十", "百", "千"];
+
+/// 数字读法
+#[derive(Clone, Copy, PartialEq)]
+enum Reading {
+    Cardinal, // 基数：十二、一百零五
+    Digits,   // 逐字：一九四九
+    Keep,     // 保留阿拉伯数字
+}
+
+/// 按数字转换方式转换文本中的连续数字
+/// 按上下文：后接“年”且三位以上按逐字读，其余四位以内按基数读，更长的按逐字读
+/// 逐字：每个数字单独转换；保留：不转换
+/// 显式标记 {数:12}、{年:1949}、{原:2024} 优先于上下文
+pub fn convert_numbers(text: &str, mode: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some((_, _, len)) = ruby::parse_ruby(&chars[i..]) {
+            // 注音（如 zi4）中的数字不转换
+            result.extend(&chars[i..i + len]);
+            i += len;
+            continue;
+        }
+        if let Some((reading, inner, len)) = parse_markup(&chars[i..]) {
+            match reading {
+                // 保留时原样输出，不做全角半角转换
+                Reading::Keep => result.extend(inner),
+                _ => {
+                    let digits: Vec<u8> = inner.iter().map(|c| digit_value(*c)).collect();
+                    result.push_str(&read_digits(&digits, reading));
+                }
+            }
+            i += len;
+            continue;
+        }
+        if !is_digit(chars[i]) {
+            result.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && is_digit(chars[i]) {
+            i += 1;
+        }
+        let digits: Vec<u8> = chars[start..i].iter().map(|c| digit_value(*c)).collect();
+        let reading = match mode {
+            "逐字" => Reading::Digits,
+            "保留" => Reading::Keep,
+            _ => reading_by_context(&digits, chars.get(i).copied()),
+        };
+        match reading {
+            Reading::Keep => result.extend(&chars[start..i]),
+            _ => result.push_str(&read_digits(&digits, reading)),
+        }
+    }
+    result
+}
+
+fn is_digit(c: char) -> bool {
+    c.is_ascii_digit() || ('０'..='９').contains(&c)
+}
+
+fn digit_value(c: char) -> u8 {
+    if c.is_ascii_digit() {
+        c as u8 - b'0'
+    } else {
+        (c as u32 - '０' as u32) as u8
+    }
+}
+
+fn reading_by_context(digits: &[u8], next: Option<char>) -> Reading {
+    if next == Some('年') && digits.len() >= 3 {
+        // 年份：一九四九年
+        Reading::Digits
+    } else if digits.len() <= 4 && (digits.len() == 1 || digits[0] != 0) {
+        // 序数、数量：第十二章、十二卷
+        Reading::Cardinal
+    } else {
+        // 编号等长数字及前导零
+        Reading::Digits
+    }
+}
+
+// 解析 {数:12}、{年:1949}、{原:2024}，返回读法、标记内的数字和标记长度
+fn parse_markup(chars: &[char]) -> Option<(Reading, &[char], usize)> {
+    if chars.len() < 5 || chars[0] != '{' || chars[2] != ':' {
+        return None;
+    }
+    let reading = match chars[1] {
+        '数' => Reading::Cardinal,
+        '年' => Reading::Digits,
+        '原' => Reading::Keep,
+        _ => return None,
+    };
+    // 数字之后必须紧接 }，否则不是标记
+    let end = 3 + chars[3..].iter().position(|c| !is_digit(*c))?;
+    if end == 3 || chars[end] != '}' {
+        return None;
+    }
+    Some((reading, &chars[3..end], end + 1))
+}
+
+fn read_digits(digits: &[u8], reading: Reading) -> String {
+    match reading {
+        Reading::Keep => digits.iter().map(|d| char::from(b'0' + d)).collect(),
+        Reading::Digits => digits.iter().map(|d| DIGITS[*d as usize]).collect(),
+        Reading::Cardinal if digits.len() <= 4 => {
+            let value = digits.iter().fold(0u32, |acc, d| acc * 10 + *d as u32);
+            cardinal(value)
+        }
+        // 超出千位时按逐字读
+        Reading::Cardinal => digits.iter().map(|d| DIGITS[*d as usize]).collect(),
+    }
+}
+
+// 万以内的基数读法：10 读作“十”，105 读作“一百零五”
+fn cardinal(value: u32) -> String {
+    if value == 0 {
+        return CARDINAL_DIGITS[0].to_string();
+    }
+    if (10..20).contains(&value) {
+        let ones = value % 10;
+        return if ones == 0 { "十".to_string() } else { format!("十{}", CARDINAL_DIGITS[ones as usize]) };
+    }
+    let mut result = String::new();
+    let mut pending_zero = false;
+    for pos in (0..4).rev() {
+        let d = (value / 10u32.pow(pos)) % 10;
+        if d == 0 {
+            pending_zero = !result.is_empty();
+            continue;
+        }
+        if pending_zero {
+            result.push_str(CARDINAL_DIGITS[0]);
+            pending_zero = false;
+        }
+        result.push_str(CARDINAL_DIGITS[d as usize]);
+        result.push_str(CARDINAL_UNITS[pos as usize]);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_reading() {
+        assert_eq!(convert_numbers("第12章", "按上下文"), "第十二章");
+        assert_eq!(convert_numbers("1949年", "按上下文"), "一九四九年");
+        assert_eq!(convert_numbers("105卷", "按上下文"), "一百零五卷");
+        assert_eq!(convert_numbers("10人", "按上下文"), "十人");
+        // 三位以下的年份按基数读，前导零和五位以上按逐字读
+        assert_eq!(convert_numbers("12年", "按上下文"), "十二年");
+        assert_eq!(convert_numbers("007号", "按上下文"), "〇〇七号");
+        assert_eq!(convert_numbers("123456", "按上下文"), "一二三四五六");
+        assert_eq!(convert_numbers("第１２章", "按上下文"), "第十二章");
+    }
+
+    #[test]
+    fn digit_and_keep_modes() {
+        assert_eq!(convert_numbers("第12章", "逐字"), "第一二章");
+        assert_eq!(convert_numbers("第12章1949年", "保留"), "第12章1949年");
+        assert_eq!(convert_numbers("第１２章", "保留"), "第１２章");
+    }
+
+    #[test]
+    fn explicit_markup() {
+        assert_eq!(convert_numbers("{年:12}", "按上下文"), "一二");
+        assert_eq!(convert_numbers("{数:1949}年", "按上下文"), "一千九百四十九年");
+        assert_eq!(convert_numbers("{原:2024}年", "按上下文"), "2024年");
+        assert_eq!(convert_numbers("{原:２０２４}年", "逐字"), "２０２４年");
+        // 不是标记时按普通文字处理
+        assert_eq!(convert_numbers("{数:3 }", "按上下文"), "{数:三 }");
+        assert_eq!(convert_numbers("{数:}", "按上下文"), "{数:}");
+    }
+}
@@ -0,0 +1,120 @@
+// PDF 文档元数据：printpdf 保存后补写文档信息字典、XMP 元数据流和文档语言
+use lopdf::{Dictionary, Object, Stream, StringFormat};
+use crate::config::*;
+use crate::utils::escape_xml;
+use std::error::Error;
+
+/// 在已保存的 PDF 中写入书名、作者、朝代、整理者、主题、关键词、版本、底本来源，
+/// 同时写入同样内容的 XMP 元数据，并设置文档语言（zh-Hant、zh-Hans）
+pub fn write_metadata(bytes: &[u8], fileinfo: &FileInfo, language: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut doc = lopdf::Document::load_mem(bytes)?;
+
+    // 文档信息字典：标准键之外的朝代、整理者、版本、来源用自定义键
+    let mut info = Dictionary::new();
+    let entries = [
+        ("Title", fileinfo.name.as_str()),
+        ("Author", fileinfo.author.as_str()),
+        ("Subject", fileinfo.subject.as_str()),
+        ("Keywords", fileinfo.keywords.as_str()),
+        ("Dynasty", fileinfo.dynasty.as_str()),
+        ("Editor", fileinfo.editor.as_str()),
+        ("Edition", fileinfo.edition.as_str()),
+        ("Source", fileinfo.source.as_str()),
+    ];
+    for (key, value) in entries {
+        if !value.trim().is_empty() {
+            info.set(key, text_string(value.trim()));
+        }
+    }
+    info.set("Creator", text_string(APP_NAME));
+    match doc.trailer.get(b"Info").and_then(Object::as_reference) {
+        Ok(info_id) => {
+            // 保留 printpdf 写入的制作程序、日期等
+            let existing = doc.get_dictionary_mut(info_id)?;
+            for (key, value) in info.iter() {
+                existing.set(key.clone(), value.clone());
+            }
+        }
+        Err(_) => {
+            let info_id = doc.add_object(info);
+            doc.trailer.set("Info", Object::Reference(info_id));
+        }
+    }
+
+    // XMP 元数据流不压缩，便于检索工具直接读取
+    let mut stream_dict = Dictionary::new();
+    stream_dict.set("Type", Object::Name(b"Metadata".to_vec()));
+    stream_dict.set("Subtype", Object::Name(b"XML".to_vec()));
+    let xmp = xmp_packet(fileinfo, language);
+    let metadata_id = doc.add_object(Stream::new(stream_dict, xmp.into_bytes()));
+
+    let root_id = doc.trailer.get(b"Root")?.as_reference()?;
+    let catalog = doc.get_dictionary_mut(root_id)?;
+    catalog.set("Metadata", Object::Reference(metadata_id));
+    catalog.set("Lang", Object::String(language.as_bytes().to_vec(), StringFormat::Literal));
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output)?;
+    Ok(output)
+}
+
+// 文本字符串用带字节序标记的 UTF-16BE 编码，中文在各阅读器中都能正确显示
+fn text_string(text: &str) -> Object {
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+// 关键词以中英文逗号、顿号、分号或空白分隔
+fn split_keywords(keywords: &str) -> Vec<&str> {
+    keywords
+        .split(|c: char| matches!(c, ',' | '，' | '、' | ';' | '；') || c.is_whitespace())
+        .filter(|keyword| !keyword.is_empty())
+        .collect()
+}
+
+// 按 Dublin Core 写书名、作者、整理者、主题、关键词、来源和语言，版本用 PRISM，朝代用自定义命名空间
+fn xmp_packet(fileinfo: &FileInfo, language: &str) -> String {
+    let mut fields = String::new();
+    let mut push = |element: &str, value: &str| {
+        if !value.trim().is_empty() {
+            fields.push_str(&element.replace("{}", &escape_xml(value.trim())));
+            fields.push('\n');
+        }
+    };
+    push(r#"<dc:title><rdf:Alt><rdf:li xml:lang="x-default">{}</rdf:li></rdf:Alt></dc:title>"#, &fileinfo.name);
+    push("<dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>", &fileinfo.author);
+    push("<dc:contributor><rdf:Bag><rdf:li>{}</rdf:li></rdf:Bag></dc:contributor>", &fileinfo.editor);
+    push(r#"<dc:description><rdf:Alt><rdf:li xml:lang="x-default">{}</rdf:li></rdf:Alt></dc:description>"#, &fileinfo.subject);
+    push("<dc:source>{}</dc:source>", &fileinfo.source);
+    push("<dc:language><rdf:Bag><rdf:li>{}</rdf:li></rdf:Bag></dc:language>", language);
+    push("<pdf:Keywords>{}</pdf:Keywords>", &fileinfo.keywords);
+    push("<prism:edition>{}</prism:edition>", &fileinfo.edition);
+    push("<ab:dynasty>{}</ab:dynasty>", &fileinfo.dynasty);
+    push("<xmp:CreatorTool>{}</xmp:CreatorTool>", APP_NAME);
+    let keywords = split_keywords(&fileinfo.keywords);
+    if !keywords.is_empty() {
+        let items: String = keywords
+            .iter()
+            .map(|keyword| format!("<rdf:li>{}</rdf:li>", escape_xml(keyword)))
+            .collect();
+        fields.push_str(&format!("<dc:subject><rdf:Bag>{}</rdf:Bag></dc:subject>\n", items));
+    }
+    format!(
+        r#"<?xpacket begin="{}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+<rdf:Description rdf:about=""
+ xmlns:dc="http://purl.org/dc/elements/1.1/"
+ xmlns:pdf="http://ns.adobe.com/pdf/1.3/"
+ xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+ xmlns:prism="http://prismstandard.org/namespaces/basic/2.0/"
+ xmlns:ab="urn:ancientbook:metadata:1.0">
+{}</rdf:Description>
+</rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        '\u{feff}',
+        fields
+    )
+}
@@ -1,26 +1,90 @@
 use printpdf::*;
-use crate::utils;
+use crate::utils::{self, Line};
+use crate::convert::{self, Converter};
+use crate::numeral;
+use crate::ruby;
+use crate::metrics::{FontMetrics, VerticalGlyph};
 use crate::config::*;
+use crate::woodblock::{self, Jitter};
+use crate::imposition;
+use crate::pdfmeta;
 use std::path::Path;
 use std::sync::Arc;
 use rayon::prelude::*;
-pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {    
+/// 排好的全书页面及其用到的字体、图像，PDF、PNG 导出共用
+pub struct PagedBook {
+    pub doc: PdfDocument,
+    pub name: String,
+    pub author: String,
+    pub language: &'static str,                    // 语言标签：zh-Hant、zh-Hans
+    pub pages: Vec<PdfPage>,
+    pub page_width_mm: f32,
+    pub page_height_mm: f32,
+    pub fonts: Vec<(FontId, Vec<u8>, usize)>,      // 字体编号、字体文件、字体集索引
+    pub images: Vec<(XObjectId, &'static [u8])>,   // 图像编号、PNG 数据
+    pub layers: Vec<(LayerInternalId, String)>,     // 图层编号、图层名
+    pub fold_x_mm: Option<f32>,                     // 折页线距页面左边的距离，纵向单页不折为 None
+    pub bg_color: Color,                            // 底色，出血与空白页用
+    pub bg_layer: LayerInternalId,                  // 背景层
+}
+
+pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo,style:&Style,typeset:&Typeset) {    
+    let output_path = fileinfo.outputpath.as_str();
+    let Some(mut book) = layout_book(page, drawcolor, fileinfo, style, typeset) else {
+        return;
+    };
+    // 拼版和印刷标记只用于 PDF，PNG、SVG 导出仍为成品页面
+    // 纵向单页拼版：骑马订全书一帖，二拼每帖4页，四拼每帖8页
+    if page.page_width_mm <= page.page_height_mm {
+        match style.imposition.as_str() {
+            "骑马订" => imposition::impose_signatures(&mut book, None, 1),
+            "二拼" => imposition::impose_signatures(&mut book, Some(4), 1),
+            "四拼" => imposition::impose_signatures(&mut book, Some(8), 2),
+            _ => {}
+        }
+    }
+    if style.print_mode == "线装印刷" {
+        imposition::add_print_marks(&mut book, style.bleed_mm, fileinfo.creater.as_str());
+    }
+    let PagedBook { mut doc, name, author, language, pages, .. } = book;
+    // 将所有页面添加到文档
+    doc.with_pages(pages);
+    println!("正在保存 {}", output_path);
+    // 保存PDF文件
+    let bytes = doc.save(&PdfSaveOptions::default(), &mut Vec::new());
+    // 补全文档信息与 XMP 元数据，EPUB 输入时书名、作者取自其元数据
+    let mut fileinfo = fileinfo.clone();
+    fileinfo.name = name;
+    fileinfo.author = author;
+    let bytes = match pdfmeta::write_metadata(&bytes, &fileinfo, language) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            println!("写入文档元数据失败: {:?}", e);
+            bytes
+        }
+    };
+    
+    std::fs::write(output_path, bytes)
+        .expect("Failed to write PDF file");    
+    println!("Created {}", output_path);
+}
+
+/// 排版全书，生成各页的绘制指令；输入文件读取失败时返回 None
+pub fn layout_book(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo,style:&Style,typeset:&Typeset) -> Option<PagedBook> {    
     
     // 设置主要参数
     let page_width_mm = page.page_width_mm;
     let page_height_mm = page.page_height_mm;
     
     let column_count = page.column_count  as usize;    
-    let book_name = fileinfo.name.as_str();
     let book_creater = fileinfo.creater.as_str();
     let main_font_path = fileinfo.main_path.as_str();
     let backup_font_path = fileinfo.backup_path.as_str();
     let input_path = fileinfo.inputpath.as_str();
-    let output_path = fileinfo.outputpath.as_str();
     
-    let content = get_content_loc(&page);
-    let pagination = get_pagination_loc(&page);
-    let title = get_title_loc(&page);
+    let content = get_content_loc(&page, typeset);
+    let pagination = get_pagination_loc(&page, typeset);
+    let title = get_title_loc(&page, typeset);
 
     let count_per_column = content.max_chars  as usize;
     let fontcolor = color_to_rgb(&drawcolor.draw.as_str());
@@ -28,23 +92,48 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
     let linecolor = color_to_rgb(&drawcolor.line.as_str());
     //  direction: 0 横向 1 纵向
     let direction: u8 = if page_width_mm > page_height_mm {0} else {1};
-    let canvas = if direction == 0 { get_canvas_horizontal(&page) } else { get_canvas_vertical(&page) };
-    let tail = if direction == 0 { get_tail_horizontal(&page) } else { get_tail_vertical(&page)};
+    let canvas = if direction == 0 { get_canvas_horizontal(&page, style) } else { get_canvas_vertical(&page, style) };
+    let tail = if direction == 0 { get_tail_horizontal(&page, style) } else { get_tail_vertical(&page, style)};
+    let ear = get_ear_loc(&canvas, style, typeset);
+    // 版心中缝距页面左边的距离，线装印刷在此加折页线
+    let fold_x_mm = (canvas.point_center_left_top.x + canvas.point_center_right_top.x).0 / 2.0 / MM_TO_PT;
     // 检查输入文件是否存在
     if !Path::new(&input_path).exists() {
         println!("错误：输入文件不存在: {}", input_path);
-        return;
+        return None;
     }    
-    // 获取文本内容，处理可能的错误
-    let text = match utils::get_txt(&input_path) {
-        Ok(content) => content,
+    // 读取文本并解析为文档树，EPUB 未填书名、作者时取其元数据
+    let mut fileinfo = fileinfo.clone();
+    let doc = match utils::load_document(&mut fileinfo, typeset) {
+        Ok(doc) => doc,
         Err(e) => {
             println!("读取输入文件失败: {:?}", e);
-            return;
+            return None;
         }
     };
+    let book_name = fileinfo.name.as_str();
     // 生成[章][页][行]数组
-    let txt_pages = utils::process_text(&text, count_per_column, column_count);
+    // 字符替换表，正文、标题、页码、书耳共用
+    let substituter = Converter::from_substitutions(&typeset.substitutions);
+    // 拉丁文字用备用字体排出，按备用字体度量其长度
+    let backup_font_bytes = std::fs::read(&backup_font_path).unwrap();
+    let backup_metrics = FontMetrics::from_bytes(&backup_font_bytes, 1);
+    let latin_cells = |run: &str| backup_metrics.cells(run, content.font_size_pt, -content.space_y_pt.0);
+    let txt_pages = utils::layout_document(&doc, count_per_column, column_count, typeset, &substituter, &latin_cells);
+    // 文档语言：转换后按目标字体，不转换时由正文用字判断
+    let language = match typeset.script_convert.as_str() {
+        "简转繁" => "zh-Hant",
+        "繁转简" => "zh-Hans",
+        _ => {
+            let text: String = txt_pages.iter().flatten().flatten().map(|line| line.text.as_str()).collect();
+            convert::detect_language(&text)
+        }
+    };
+    // 书耳文字，按章取值
+    let ear_texts: Vec<String> = txt_pages
+        .iter()
+        .map(|chap| get_ear_text(style, typeset, book_name, chap, &substituter))
+        .collect();
     //pdf文档参数初始化
     let mut doc = PdfDocument::new(book_name);
     
@@ -64,21 +153,32 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
         usage: LayerSubtype::Artwork,
     };
     let text_layer_id = doc.add_layer(&text_layer);
+    // SVG 导出按图层名分组
+    let layers = vec![
+        (background_layer_id.clone(), background_layer.name.clone()),
+        (text_layer_id.clone(), text_layer.name.clone()),
+    ];
     // 增加主字体
     let main_font_bytes = std::fs::read(&main_font_path).unwrap();
     let font_byte_slice_main: &[u8] = &main_font_bytes;
+    // 正文字符按主字体的字形外框居中
+    let main_metrics = FontMetrics::from_bytes(&main_font_bytes, 0);
     let main_font =
         printpdf::ParsedFont::from_bytes(font_byte_slice_main, 0, 
                                &mut Vec::new()).unwrap();
     let main_font_id: FontId = doc.add_font(&main_font);
     // 增加备用字体
-    let backup_font_bytes = std::fs::read(&backup_font_path).unwrap();
     let font_byte_slice_backup: &[u8] = &backup_font_bytes;
     let backup_font =
         printpdf::ParsedFont::from_bytes(font_byte_slice_backup, 1, 
                                &mut Vec::new()).unwrap();
     let backup_font_id = doc.add_font(&backup_font);
     let cover_font_id = main_font_id.clone();
+    // 栅格化时按字体编号取回字体文件
+    let fonts = vec![
+        (main_font_id.clone(), main_font_bytes.clone(), 0),
+        (backup_font_id.clone(), backup_font_bytes.clone(), 1),
+    ];
 
     //let mut pages = vec![];
     // 将不可变参数包装成Arc，以便在多线程间安全共享
@@ -87,6 +187,14 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
     let pagination_arc = Arc::new(pagination.clone());
     let content_arc = Arc::new(content.clone());
     let title_arc = Arc::new(title);
+    let style_arc = Arc::new(style.clone());
+    let ear_arc = Arc::new(ear);
+    let ear_texts_arc = Arc::new(ear_texts);
+    let substituter_arc = Arc::new(substituter);
+    let main_metrics_arc = Arc::new(main_metrics);
+    let backup_metrics_arc = Arc::new(backup_metrics);
+    // 标题中的数字与正文一样转换
+    let title_name = numeral::convert_numbers(book_name, typeset.number_mode.as_str());
 
     let main_font_arc = Arc::new(main_font_id);
     let backup_font_arc = Arc::new(backup_font_id);
@@ -106,6 +214,12 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
             let pagination_arc_clone = Arc::clone(&pagination_arc);
             let content_arc_clone = Arc::clone(&content_arc);
             let title_arc_clone = Arc::clone(&title_arc);
+            let style_arc_clone = Arc::clone(&style_arc);
+            let ear_arc_clone = Arc::clone(&ear_arc);
+            let ear_texts_arc_clone = Arc::clone(&ear_texts_arc);
+            let substituter_arc_clone = Arc::clone(&substituter_arc);
+            let main_metrics_arc_clone = Arc::clone(&main_metrics_arc);
+            let backup_metrics_arc_clone = Arc::clone(&backup_metrics_arc);
             let main_font_arc_clone = Arc::clone(&main_font_arc);
             let backup_font_arc_clone = Arc::clone(&backup_font_arc);
             let bg_layer_arc_clone = Arc::clone(&bg_layer_arc);
@@ -115,7 +229,8 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
             let linecolor_outer = linecolor.clone();
             let fontcolor_outer = fontcolor.clone();
             let direction_outer = direction.clone();
-            let book_name_outer = book_name.clone();
+            let book_name_outer = title_name.clone();
+            let number_mode_outer = typeset.number_mode.clone();
             pages.par_iter()
                  .enumerate() // 页索引 (page_idx)
                  .map(move |(page_idx, lines)| {
@@ -124,6 +239,11 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
                 let pagination_clone = Arc::clone(&pagination_arc_clone);
                 let content_clone = Arc::clone(&content_arc_clone);
                 let title_clone = Arc::clone(&title_arc_clone);
+                let style_clone = Arc::clone(&style_arc_clone);
+                let ear_clone = Arc::clone(&ear_arc_clone);
+                let substituter_clone = Arc::clone(&substituter_arc_clone);
+                let main_metrics_clone = Arc::clone(&main_metrics_arc_clone);
+                let backup_metrics_clone = Arc::clone(&backup_metrics_arc_clone);
                 let main_font_clone = Arc::clone(&main_font_arc_clone);
                 let backup_font_clone = Arc::clone(&backup_font_arc_clone);
                 let bg_layer_clone = Arc::clone(&bg_layer_arc_clone);
@@ -135,7 +255,10 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
                 let bgcolor_clone = bgcolor_outer.clone();
                 let direction_clone = direction_outer.clone();
                 let book_name_clone = book_name_outer.to_string();
+                let number_mode_clone = number_mode_outer.clone();
                 
+                // 刻本效果按章、页生成独立的随机序列
+                let mut jitter = Jitter::new(&style_clone, woodblock::page_salt(chap_idx, page_idx));
                 let mut ops: Vec<Op> = Vec::new();            
                 // 制作模板（背景层）            
                 ops.append(&mut add_template(
@@ -143,20 +266,24 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
                             page_height_mm,
                             &canvas_clone,
                             &tail_clone,
+                            &ear_clone,
                             bgcolor_clone,
                             linecolor_clone,
                             column_count,
                             direction_clone,
+                            &mut jitter,
                             bg_layer_clone.as_ref().clone()));
                 // 处理文本内容（文本层）
                 ops.push(Op::BeginLayer {layer_id: txt_layer_clone.as_ref().clone(),});
                 // 添加页码
                 let total_page_idx = total_pages_before + page_idx + 1;
                 let page_num_ops = add_pagenumber_text(                
-                    &format!("{}", total_page_idx), 
+                    &numeral::convert_numbers(&total_page_idx.to_string(), &number_mode_clone),
                     &pagination_clone,
                     &fontcolor_clone,
-                    &backup_font_clone
+                    &backup_font_clone,
+                    &substituter_clone,
+                    &mut jitter
                 );
                 ops.extend(page_num_ops);          
                 // 添加标题            
@@ -165,9 +292,21 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
                     &book_name_clone, 
                     &fontcolor_clone,               
                     &main_font_clone,
-                    &backup_font_clone
+                    &backup_font_clone,
+                    &substituter_clone,
+                    &mut jitter
                 );
                 ops.extend(title_ops);
+                // 添加书耳文字
+                if ear_clone.visible {
+                    ops.extend(add_ear_text(
+                        &ear_clone,
+                        &ear_texts_arc_clone[chap_idx],
+                        &fontcolor_clone,
+                        &main_font_clone,
+                        &mut jitter
+                    ));
+                }
                 
                 // 添加内容
                 let content_ops = add_centent_text(
@@ -175,7 +314,10 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
                     &content_clone,
                     &fontcolor_clone,
                     &main_font_clone,
-                    &backup_font_clone
+                    &backup_font_clone,
+                    &main_metrics_clone,
+                    &backup_metrics_clone,
+                    &mut jitter
                 );
                 ops.extend(content_ops);
                 
@@ -200,11 +342,13 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
     sort_processed_pages(&mut pages);
     // 排序后直接提取 PdfPage（顺序已保证）
     let mut ordered_pages: Vec<PdfPage> = pages.into_iter().map(|(_, _, page)| page).collect();
+    let mut images = Vec::new();
     //  direction: 0 横向 1 纵向
     if direction == 1 {
-        let image_bytes = include_bytes!("./cover.png");
+        let image_bytes: &'static [u8] = include_bytes!("./cover.png");
         let image = RawImage::decode_from_bytes(image_bytes, &mut Vec::new()).unwrap();        
         let image_id = doc.add_image(&image);
+        images.push((image_id.clone(), image_bytes));
         let cover_txt = book_name;
         let first_page = add_cover(image_id, 
                         cover_font_id,
@@ -215,15 +359,23 @@ pub fn create_pdf(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
         ordered_pages.insert(0, first_page);
         
     }    
-    // 将所有页面添加到文档
-    doc.with_pages(ordered_pages);
-    println!("正在保存 {}", output_path);
-    // 保存PDF文件
-    let bytes = doc.save(&PdfSaveOptions::default(), &mut Vec::new());
-    
-    std::fs::write(output_path, bytes)
-        .expect("Failed to write PDF file");    
-    println!("Created {}", output_path);
+    let book = PagedBook {
+        doc,
+        name: book_name.to_string(),
+        author: fileinfo.author.clone(),
+        language,
+        pages: ordered_pages,
+        page_width_mm,
+        page_height_mm,
+        fonts,
+        images,
+        layers,
+        // 横向页对折于版心
+        fold_x_mm: if direction == 0 { Some(fold_x_mm) } else { None },
+        bg_color: bgcolor.clone(),
+        bg_layer: bg_layer_arc.as_ref().clone(),
+    };
+    Some(book)
 }
 
 // 按 (章索引, 页索引) 升序排序
@@ -286,7 +438,7 @@ fn add_cover(image_id: XObjectId,
      ops1
     )
 }
-pub fn create_pdf_template(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo) {
+pub fn create_pdf_template(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo,style:&Style,typeset:&Typeset) {
 
     let page_width_mm = page.page_width_mm;
     let page_height_mm = page.page_height_mm;
@@ -296,9 +448,10 @@ pub fn create_pdf_template(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo)
     let book_name = fileinfo.name.as_str();
     let direction: u8 = if page_width_mm > page_height_mm {0} else {1};
 
-    let canvas = if direction == 0 { get_canvas_horizontal(&page) } else { get_canvas_vertical(&page) };
+    let canvas = if direction == 0 { get_canvas_horizontal(&page, style) } else { get_canvas_vertical(&page, style) };
 
-    let tail = if direction == 0 { get_tail_horizontal(&page) } else { get_tail_vertical(&page)}; 
+    let tail = if direction == 0 { get_tail_horizontal(&page, style) } else { get_tail_vertical(&page, style)}; 
+    let ear = get_ear_loc(&canvas, style, typeset);
     let mut doc = PdfDocument::new(book_name);
     //设置背景层
     let background_layer = Layer {
@@ -308,16 +461,19 @@ pub fn create_pdf_template(page: &Pager,drawcolor:&DrawColor,fileinfo:&FileInfo)
         usage: LayerSubtype::Artwork,
     };
     let background_layer_id = doc.add_layer(&background_layer);
+    let mut jitter = Jitter::new(style, woodblock::page_salt(0, 0));
     let mut ops: Vec<Op> = Vec::new();            
             // 制作模板（背景层）            
     ops.append(&mut add_template(page_width_mm,
                 page_height_mm,
                 &canvas,
                 &tail,
+                &ear,
                 bgcolor,
                 linecolor,
                 column_count,
                 direction,
+                &mut jitter,
                 background_layer_id.clone()));
     let ordered_pages = PdfPage::new(
         Mm(page_width_mm),
@@ -349,11 +505,80 @@ fn add_vertical_text(
     ops.push(Op::WriteText {items: vec![TextItem::Text(char.to_string())],font: fontid.clone()});   
     ops
 }
+// 添加带刻本效果扰动的字符，未开启时与 add_vertical_text 相同
+fn add_jitter_text(
+        jitter: &mut Jitter,
+        fontcolor: &Color,
+        fontid: &FontId,
+        fontsize: f32,
+        char_x: Pt,
+        char_y: Pt,
+        char : &str,
+        char_rotate: f32,)->Vec<Op>{
+
+    if !jitter.enabled() {
+        return add_vertical_text(fontid, fontsize, char_x, char_y, char, char_rotate);
+    }
+    let j = jitter.glyph(fontsize);
+    let mut ops = vec![];
+    ops.push(Op::SetFillColor { col: woodblock::fade_color(fontcolor, j.ink) });
+    ops.append(&mut add_vertical_text(fontid,
+                    fontsize * j.size_scale,
+                    char_x + j.dx,
+                    char_y + j.dy,
+                    char,
+                    char_rotate + j.angle));
+    ops
+}
+// 按字形编号绘制竖排字形，文字提取时仍对应原字符
+fn add_vertical_glyph(
+        fontid: &FontId,
+        fontsize: f32,
+        char_x: Pt,
+        char_y: Pt,
+        glyph_id: u16,
+        char: char,
+        char_rotate: f32,)->Vec<Op>{
+
+    let mut ops = vec![];
+    ops.push(Op::SetFontSize { font: fontid.clone(), size: Pt(fontsize) });    
+    ops.push(Op::SetTextMatrix {matrix: TextMatrix::TranslateRotate(char_x, char_y, char_rotate) });
+    ops.push(Op::WriteCodepoints {font: fontid.clone(), cp: vec![(glyph_id, char)]});
+    ops
+}
+// 带刻本效果扰动的竖排字形，未开启时与 add_vertical_glyph 相同
+fn add_jitter_glyph(
+        jitter: &mut Jitter,
+        fontcolor: &Color,
+        fontid: &FontId,
+        fontsize: f32,
+        char_x: Pt,
+        char_y: Pt,
+        glyph_id: u16,
+        char: char,)->Vec<Op>{
+
+    if !jitter.enabled() {
+        return add_vertical_glyph(fontid, fontsize, char_x, char_y, glyph_id, char, 0.0);
+    }
+    let j = jitter.glyph(fontsize);
+    let mut ops = vec![];
+    ops.push(Op::SetFillColor { col: woodblock::fade_color(fontcolor, j.ink) });
+    ops.append(&mut add_vertical_glyph(fontid,
+                    fontsize * j.size_scale,
+                    char_x + j.dx,
+                    char_y + j.dy,
+                    glyph_id,
+                    char,
+                    j.angle));
+    ops
+}
 fn add_pagenumber_text(
         text: &str,
         pagination: &Pagination,
         fontcolor:&Color,
-        font_id: &FontId)->Vec<Op>{
+        font_id: &FontId,
+        substituter: &Converter,
+        jitter: &mut Jitter)->Vec<Op>{
 
     let mut ops = vec![];
 
@@ -363,16 +588,17 @@ fn add_pagenumber_text(
 
     let loc_start_x_pt = pagination.loc_start_x_pt;
     let loc_start_y_pt = pagination.loc_start_y_pt;
-    let space_y_pt = Pt(pagination.font_size_pt * FONT_OFFSET_SCALE);
+    let space_y_pt = pagination.space_y_pt;
     let mut char_x;
     let mut char_y;
     //let char_x = Pt(400.0);
     //let char_y = Pt(447.0);
-    for (i, char) in text.chars().enumerate(){
-        let char_content: char = utils::replace_char(char);
+    for (i, char_content) in substituter.convert(text).chars().enumerate(){
         char_x = loc_start_x_pt;
         char_y = loc_start_y_pt - space_y_pt * i as f32;
-        ops.append(&mut add_vertical_text(font_id, 
+        ops.append(&mut add_jitter_text(jitter,
+                            fontcolor,
+                            font_id, 
                             fontsize, 
                             char_x, 
                             char_y, 
@@ -387,7 +613,9 @@ fn add_title_text(
         txt: &str, 
         fontcolor:&Color,       
         font_id: &FontId,
-        font_backup_id: &FontId)->Vec<Op>{
+        font_backup_id: &FontId,
+        substituter: &Converter,
+        jitter: &mut Jitter)->Vec<Op>{
         
     let mut ops = vec![];
 
@@ -399,23 +627,22 @@ fn add_title_text(
     //let txt = "庄子";
     let mut char_x;
     let mut char_y;
-    for (i, char) in txt.chars().enumerate(){
+    for (i, char_content) in substituter.convert(txt).chars().enumerate(){
         if (i + 1) as i32 >= t.max_chars {
             break;
         }
         char_x =t.loc_start_x_pt;
         char_y = t.loc_start_y_pt + t.space_y_pt * i as f32;
-        let char_content: char = utils::replace_char(char);
         match utils::is_punctuation(char_content) {
             0 => {// 是无读字符 
-                    ops.append(&mut add_vertical_text(font_backup_id, 
+                    ops.append(&mut add_jitter_text(jitter, fontcolor, font_backup_id, 
                         fontsize, 
                         char_x, char_y, 
                         &char_content.to_string(), 
                         0.0));
                 }
             _ => {
-                    ops.append(&mut add_vertical_text(font_id, 
+                    ops.append(&mut add_jitter_text(jitter, fontcolor, font_id, 
                         fontsize, 
                         char_x, char_y, 
                         &char_content.to_string(), 
@@ -426,16 +653,52 @@ fn add_title_text(
     ops
 }
 
+// 书耳文字：书名、章名（章首行）或自定义文字，章名已在分行时替换过
+fn get_ear_text(style: &Style, typeset: &Typeset, book_name: &str, chapter: &[Vec<Line>], substituter: &Converter) -> String {
+    let number_mode = typeset.number_mode.as_str();
+    match style.ear_source.as_str() {
+        "书名" => substituter.convert(&numeral::convert_numbers(book_name, number_mode)),
+        "自定义" => substituter.convert(&numeral::convert_numbers(&style.ear_text, number_mode)),
+        _ => chapter
+            .first()
+            .and_then(|page| page.first())
+            .map(|line| ruby::strip_markup(line.text.trim()))
+            .unwrap_or_default(),
+    }
+}
+
+fn add_ear_text(
+        ear: &Ear,
+        txt: &str,
+        fontcolor: &Color,
+        font_id: &FontId,
+        jitter: &mut Jitter)->Vec<Op>{
+
+    let mut ops = vec![];
+    ops.push(Op::SetFillColor { col: fontcolor.clone() });
+    for (i, char_content) in txt.chars().take(ear.max_chars).enumerate(){
+        ops.append(&mut add_jitter_text(jitter, fontcolor, font_id,
+                        ear.font_size_pt,
+                        ear.loc_start_x_pt,
+                        ear.loc_start_y_pt - ear.space_y_pt * i as f32,
+                        &char_content.to_string(),
+                        0.0));
+    }
+    ops
+}
+
 // 添加内容文本
 fn add_centent_text(
-        texts: &[String],
+        texts: &[Line],
         content: &Content,
         fontcolor: &Color,
         font_id: &FontId,
-        font_backup_id: &FontId,)->Vec<Op>
+        font_backup_id: &FontId,
+        main_metrics: &FontMetrics,
+        backup_metrics: &FontMetrics,
+        jitter: &mut Jitter,)->Vec<Op>
     {
     let mut ops = vec![];
-    let fontsize =content.font_size_pt;
     let color_rbg = fontcolor.clone();
     ops.push(Op::SetFillColor { col: color_rbg }); 
 
@@ -443,53 +706,120 @@ fn add_centent_text(
     let mut char_y ;
     let mut loc_x_pt;
     let mut loc_y_pt;
+    // 字位数与字号无关，小字按正文字号计算即可
+    let latin_cells = |run: &str| backup_metrics.cells(run, content.font_size_pt, -content.space_y_pt.0);
 
-    for (col, linetxt) in texts.iter().enumerate(){  
+    for (col, line) in texts.iter().enumerate(){  
+        let linetxt = line.text.as_str();
         let mut count = 0; 
-        loc_x_pt = content.loc_x_pt[col];            
-        loc_y_pt = content.loc_y_pt;
+        // 注文为小字，字号、字距按比例缩小，在列内居中，字身顶端与正文对齐
+        let fontsize = content.font_size_pt * line.scale;
+        let pun_size = content.pun_font_size_pt * line.scale;
+        let shrink = content.font_size_pt - fontsize;
+        loc_x_pt = content.loc_x_pt[col] + Pt(shrink / 2.0);
+        // 低格的行从下移若干字位处起排，抬头的行高出版框；低格不超过列长
+        let indent = line.indent.clamp(-MAX_RAISE, content.max_chars - 1);
+        loc_y_pt = content.loc_y_pt + content.space_y_pt * indent as f32 + Pt(shrink);
+        let max_chars = ((content.max_chars - indent) as f32 / line.scale) as i32;
+        if max_chars <= 0 {
+            continue;
+        }
+        // 避头尾可能使本行多出一个字位：悬挂时照常排出版框，挤入时压缩字距
+        let slots = utils::line_cells(linetxt, &latin_cells) as i32;
+        let space_y_pt = if slots > max_chars && !content.hanging {
+            content.space_y_pt * line.scale * (max_chars as f32 / slots as f32)
+        } else {
+            content.space_y_pt * line.scale
+        };
 
-        for (_, char) in linetxt.chars().enumerate(){
-            if count >= content.max_chars {
+        // 注音标记不占字位，先取出正文，记下每个字所在的字位
+        let (chars, spans) = ruby::strip_ruby(linetxt);
+        let mut char_cells: Vec<Option<(i32, i32)>> = vec![None; chars.len()];
+        let mut i = 0;
+        while i < chars.len() {
+            let char = chars[i];
+            if utils::is_latin(char) {
+                // 横排片段：直立、纵中横或旋转排列
+                let end = utils::latin_run_end(&chars, i);
+                let run: String = chars[i..end].iter().collect();
+                i = end;
+                if count > max_chars {
+                    break;
+                }
+                char_y = loc_y_pt + space_y_pt * count as f32;
+                let (mut latin_ops, cells) = add_latin_text(jitter, fontcolor, font_backup_id, backup_metrics,
+                                    fontsize, loc_x_pt, char_y, space_y_pt, &run, &latin_cells);
+                ops.append(&mut latin_ops);
+                char_cells[i - run.chars().count()..i].fill(Some((count, count + cells - 1)));
+                count += cells;
+                continue;
+            }
+            i += 1;
+            let class = utils::is_punctuation(char);
+            // 最多多出一个悬挂或挤入的字位，其后的标点仍随前字排出
+            if count > max_chars && class != 1 {
                 //count = 0;
                 break;
             }
-            let char_content: char = utils::replace_char(char);
-            match utils::is_punctuation(char_content) {
+            let cell = if class == 1 { count - 1 } else { count };
+            char_cells[i - 1] = Some((cell, cell));
+            match class {
                 0 => {// 无读字符
-                    char_x = loc_x_pt;
-                    char_y = loc_y_pt + content.space_y_pt * count as f32;                    
-                    ops.append(&mut add_vertical_text(font_backup_id, 
+                    char_x = loc_x_pt + Pt(backup_metrics.center_offset_em(char) * fontsize);
+                    char_y = loc_y_pt + space_y_pt * count as f32;                    
+                    ops.append(&mut add_jitter_text(jitter, fontcolor, font_backup_id, 
                                     fontsize, 
                                     char_x, char_y,  
                                     &char.to_string(), 
                                     0.0));
                     count += 1;               
                 }
-                1 => {// 标点字符
-                    char_x = loc_x_pt + Pt(fontsize);
-                    char_y = loc_y_pt + content.space_y_pt * (count - 1) as f32;                    
-                    ops.append(&mut add_vertical_text(font_backup_id, 
-                                    content.pun_font_size_pt, 
+                1 => {// 标点字符：外框中心固定在前字右下方，不随字形留白漂移
+                    let (center_x, center_y) = backup_metrics.char_center_em(char);
+                    char_x = loc_x_pt + Pt(fontsize + (PUN_CENTER_X - center_x) * pun_size);
+                    char_y = loc_y_pt + space_y_pt * (count - 1) as f32 + Pt((PUN_CENTER_Y - center_y) * pun_size);
+                    ops.append(&mut add_jitter_text(jitter, fontcolor, font_backup_id, 
+                                    pun_size, 
                                     char_x, char_y, 
                                     &char.to_string(), 
                                     0.0));
                 }
-                3 =>{// 旋转字符
+                3 =>{// 竖排符号：优先用字体的竖排字形，其次 Unicode 竖排标点，最后旋转
                     char_x = loc_x_pt;
-                    char_y = loc_y_pt + content.space_y_pt * count as f32 + Pt(fontsize * PUN_PUB);                    
-                    ops.append(&mut add_vertical_text(font_backup_id, 
-                                    fontsize, 
-                                    char_x, char_y, 
-                                    &char.to_string(), 
-                                    -90.0));
+                    char_y = loc_y_pt + space_y_pt * count as f32;
+                    match backup_metrics.vertical_glyph(char) {
+                        VerticalGlyph::Substituted(glyph_id) => {
+                            char_x += Pt((0.5 - backup_metrics.glyph_center_em(glyph_id).0) * fontsize);
+                            char_y -= Pt(backup_metrics.vertical_shift_em(glyph_id) * fontsize);
+                            ops.append(&mut add_jitter_glyph(jitter, fontcolor, font_backup_id, 
+                                            fontsize, 
+                                            char_x, char_y, 
+                                            glyph_id, char));
+                        }
+                        VerticalGlyph::Presentation(form) => {
+                            char_x += Pt(backup_metrics.center_offset_em(form) * fontsize);
+                            ops.append(&mut add_jitter_text(jitter, fontcolor, font_backup_id, 
+                                            fontsize, 
+                                            char_x, char_y, 
+                                            &form.to_string(), 
+                                            0.0));
+                        }
+                        VerticalGlyph::Rotate => {
+                            char_y += content.pun_offset_y_pt * line.scale;
+                            ops.append(&mut add_jitter_text(jitter, fontcolor, font_backup_id, 
+                                            fontsize, 
+                                            char_x, char_y, 
+                                            &char.to_string(), 
+                                            -90.0));
+                        }
+                    }
                     count += 1;   
 
                 }
                 _ => {// 正常字符
-                    char_x = loc_x_pt;
-                    char_y = loc_y_pt + content.space_y_pt * count as f32;                    
-                    ops.append(&mut add_vertical_text(font_id, 
+                    char_x = loc_x_pt + Pt(main_metrics.center_offset_em(char) * fontsize);
+                    char_y = loc_y_pt + space_y_pt * count as f32;                    
+                    ops.append(&mut add_jitter_text(jitter, fontcolor, font_id, 
                                     fontsize, 
                                     char_x, char_y, 
                                     &char.to_string(), 
@@ -499,19 +829,117 @@ fn add_centent_text(
             }
             //print!("char: {}, char_x: {:?}, char_y:{:?}\n",char, char_x, char_y);
         }
+        // 注音排在所注正文的右侧
+        for span in &spans {
+            let (Some((first, _)), Some((_, last))) = (char_cells[span.start], char_cells[span.end - 1]) else {
+                continue;
+            };
+            ops.append(&mut add_ruby_text(jitter, fontcolor, font_id, font_backup_id, main_metrics, backup_metrics,
+                                fontsize, loc_x_pt, loc_y_pt + space_y_pt * first as f32, space_y_pt, last - first + 1, &span.gloss));
+        }
     }
     ops
 }
 
+// 注音：拼音等拉丁文字旋转排成一行，注音符号、反切逐字直排，都在所注字位范围内居中
+// char_y 为首个字位的基线，cells 为所注字位数
+fn add_ruby_text(
+        jitter: &mut Jitter,
+        fontcolor: &Color,
+        font_id: &FontId,
+        font_backup_id: &FontId,
+        main_metrics: &FontMetrics,
+        backup_metrics: &FontMetrics,
+        fontsize: f32,
+        loc_x_pt: Pt,
+        char_y: Pt,
+        space_y_pt: Pt,
+        cells: i32,
+        gloss: &str)->Vec<Op>{
+
+    let mut ops = vec![];
+    let ruby_size = fontsize * RUBY_SCALE;
+    let top_y = char_y + Pt(fontsize);
+    let span = fontsize - space_y_pt.0 * (cells - 1) as f32;
+    let left_x = loc_x_pt + Pt(fontsize * (1.0 + RUBY_GAP));
+    if ruby::is_latin_gloss(gloss) {
+        let length = backup_metrics.text_width_em(gloss) * ruby_size;
+        let char_x = left_x + Pt(backup_metrics.descent_em() * ruby_size);
+        let char_y = top_y - Pt((span - length) / 2.0);
+        ops.append(&mut add_jitter_text(jitter, fontcolor, font_backup_id, ruby_size, char_x, char_y, gloss, -90.0));
+    } else {
+        let glyphs: Vec<char> = gloss.chars().filter(|c| !c.is_whitespace()).collect();
+        let step = (span / glyphs.len() as f32).max(ruby_size);
+        let start_y = top_y - Pt((span - step * glyphs.len() as f32) / 2.0);
+        for (k, c) in glyphs.iter().enumerate() {
+            // 字在字距中垂直居中
+            let center_y = start_y - Pt(step * (k as f32 + 0.5));
+            let char_y = center_y - Pt((0.5 - main_metrics.descent_em()) * ruby_size);
+            let char_x = left_x + Pt(main_metrics.center_offset_em(*c) * ruby_size);
+            ops.append(&mut add_jitter_text(jitter, fontcolor, font_id, ruby_size, char_x, char_y, &c.to_string(), 0.0));
+        }
+    }
+    ops
+}
+
+// 排出一段拉丁文字，char_y 为首个字位的基线，返回绘制指令和占用的字位数
+fn add_latin_text(
+        jitter: &mut Jitter,
+        fontcolor: &Color,
+        font_id: &FontId,
+        metrics: &FontMetrics,
+        fontsize: f32,
+        loc_x_pt: Pt,
+        char_y: Pt,
+        space_y_pt: Pt,
+        run: &str,
+        latin_cells: &dyn Fn(&str) -> usize)->(Vec<Op>, i32){
+
+    let width_em = metrics.text_width_em(run);
+    match utils::latin_layout(run) {
+        utils::LatinLayout::Upright => {
+            let offset_em = run.chars().next().map_or(0.0, |c| metrics.center_offset_em(c));
+            let char_x = loc_x_pt + Pt(offset_em * fontsize);
+            (add_jitter_text(jitter, fontcolor, font_id, fontsize, char_x, char_y, run, 0.0), 1)
+        }
+        utils::LatinLayout::TateChuYoko => {
+            // 纵中横：宽度超过一个字时缩小字号，在字位内水平、垂直居中
+            let size = fontsize * (1.0 / width_em).min(1.0);
+            let char_x = loc_x_pt + Pt((fontsize - width_em * size) / 2.0);
+            let char_y = char_y + Pt((fontsize - size) / 2.0);
+            (add_jitter_text(jitter, fontcolor, font_id, size, char_x, char_y, run, 0.0), 1)
+        }
+        utils::LatinLayout::Rotated => {
+            // 顺时针旋转 90°：基线竖直向下，字身在基线右侧，整段在所占字位内居中
+            let cells = latin_cells(run);
+            let length = width_em * fontsize;
+            let span = -space_y_pt.0 * (cells - 1) as f32 + fontsize;
+            let char_x = loc_x_pt + Pt(metrics.descent_em() * fontsize);
+            let char_y = char_y + Pt(fontsize - (span - length) / 2.0);
+            (add_jitter_text(jitter, fontcolor, font_id, fontsize, char_x, char_y, run, -90.0), cells as i32)
+        }
+    }
+}
+
+
+// 绘制折线，开启刻本效果时带波动和断口
+fn add_line(jitter: &mut Jitter, points: &[Point], is_closed: bool) -> Vec<Op> {
+    jitter.rough_line(points, is_closed)
+        .into_iter()
+        .map(|line| Op::DrawLine { line })
+        .collect()
+}
 
 fn add_template(page_width_mm: f32, 
                     page_height_mm: f32,
                     canvas:&Canvas,
                     tail:& Tail,
+                    ear:&Ear,
                     bg_color:Color,
                     line_color:Color,
                     column_count: usize,
                     direction: u8,
+                    jitter: &mut Jitter,
                     bg_layer_id: LayerInternalId)->Vec<Op>{
     
     let mut ops = vec![];
@@ -519,7 +947,7 @@ fn add_template(page_width_mm: f32,
     //print!("绘制底色\n");
     //绘制底色
     ops.push(Op::BeginLayer {layer_id: bg_layer_id.clone()});
-    ops.push(Op::SetFillColor { col: bg_color });
+    ops.push(Op::SetFillColor { col: bg_color.clone() });
     ops.push(Op::DrawPolygon {
         polygon: printpdf::Polygon {
             rings: vec![PolygonRing {
@@ -561,288 +989,203 @@ fn add_template(page_width_mm: f32,
 
 
     //print!("绘制页面外边框\n");
-    // 绘制页面外边框    
+    // 绘制页面外边框（粗线），单边时与内边框重合
     ops.push(Op::SetOutlineColor { col: line_color.clone() });
-    ops.push(Op::SetOutlineThickness { pt: canvas.line_width_pt });
-    ops.push(Op::DrawLine { 
-        line: Line {
-            points: vec![
-                LinePoint {
-                    p: Point {
-                        x: canvas.point_left_bottom.x - canvas.line_offset_pt,
-                        y: canvas.point_left_bottom.y - canvas.line_offset_pt,
-                    },
-                    bezier: false,
-                },
-                LinePoint {
-                    p: Point {
-                        x: canvas.point_left_top.x - canvas.line_offset_pt,
-                        y: canvas.point_left_top.y + canvas.line_offset_pt,
-                    },
-                    bezier: false,
-                },
-                LinePoint {
-                    p: Point {
-                        x: canvas.point_right_top.x + canvas.line_offset_pt,
-                        y: canvas.point_right_top.y + canvas.line_offset_pt,
-                    },
-                    bezier: false,
-                },
-                LinePoint {
-                    p: Point {
-                        x: canvas.point_right_bottom.x + canvas.line_offset_pt,
-                        y: canvas.point_right_bottom.y - canvas.line_offset_pt,
-                    },
-                    bezier: false,
-                },
-            ],
-            is_closed: true,
-        } 
-    } ); 
+    if canvas.frame_visible {
+        ops.push(Op::SetOutlineThickness { pt: canvas.line_width_pt });
+        ops.extend(add_line(jitter, &[
+            Point {
+                x: canvas.point_left_bottom.x - canvas.frame_offset_x_pt,
+                y: canvas.point_left_bottom.y - canvas.frame_offset_y_pt,
+            },
+            Point {
+                x: canvas.point_left_top.x - canvas.frame_offset_x_pt,
+                y: canvas.point_left_top.y + canvas.frame_offset_y_pt,
+            },
+            Point {
+                x: canvas.point_right_top.x + canvas.frame_offset_x_pt,
+                y: canvas.point_right_top.y + canvas.frame_offset_y_pt,
+            },
+            Point {
+                x: canvas.point_right_bottom.x + canvas.frame_offset_x_pt,
+                y: canvas.point_right_bottom.y - canvas.frame_offset_y_pt,
+            },
+        ], true));
+    }
     
-    //绘制鱼尾中线
-    if direction == 0 {  
-        //print!("绘制鱼尾中线");  
-        ops.push(Op::DrawLine { 
-            line: Line {
-                points: vec![
-                    LinePoint {
-                        p: Point {
-                            x: Mm(page_width_mm / 2.0).into_pt(),
-                            y: canvas.point_center_left_bottom.y, // Bottom left
-                        },
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: Point {
-                            x: Mm(page_width_mm / 2.0).into_pt(),
-                            y: tail.point_line_down_left.y, 
-                        },
-                        bezier: false,
-                    },
-                ],
-                is_closed: false,
-            } } );
-
-        ops.push(Op::DrawLine { 
-            line: Line {
-                points: vec![
-                    LinePoint {
-                        p: Point {
-                            x: Mm(page_width_mm / 2.0).into_pt(),
-                            y: tail.point_line_up_left.y, 
-                        },
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: Point {
-                            x: Mm(page_width_mm / 2.0).into_pt(),
-                            y: canvas.point_center_left_top.y, 
-                        },  
-                        bezier: false,
-                    },
-                ],
-                is_closed: false,
-            } } );
+    //绘制象鼻（版心中线），白口不绘制
+    if tail.xiangbi_width_pt.0 > 0.0 {  
+        //print!("绘制象鼻");  
+        let center_x = (canvas.point_center_left_top.x + canvas.point_center_right_top.x) / 2.0;
+        ops.push(Op::SetOutlineThickness { pt: tail.xiangbi_width_pt });
+        ops.extend(add_line(jitter, &[
+            Point {
+                x: center_x,
+                y: canvas.point_center_left_bottom.y, // Bottom left
+            },
+            Point {
+                x: center_x,
+                y: tail.point_line_down_left.y, 
+            },
+        ], false));
+        ops.extend(add_line(jitter, &[
+            Point {
+                x: center_x,
+                y: tail.point_line_up_left.y, 
+            },
+            Point {
+                x: center_x,
+                y: canvas.point_center_left_top.y, 
+            },
+        ], false));
     }
     //print!("绘制页面内边框");
-    // 绘制页面内边框
-    ops.push(Op::SetOutlineThickness { pt: Pt(0.5) });
-    ops.push(Op::DrawLine { 
-        line: Line {
-            points: vec![
-                LinePoint {
-                    p: canvas.point_left_bottom,
-                    bezier: false,
-                },
-                LinePoint {
-                    p: canvas.point_left_top,
-                    bezier: false,
-                },
-                LinePoint {
-                    p: canvas.point_right_top,
-                    bezier: false,
-                },
-                LinePoint {
-                    p: canvas.point_right_bottom,
-                    bezier: false,
-                },
-            ],
-            is_closed: true,
-        } } ); 
-    
-    ops.push(Op::DrawLine { 
-        line: Line {
-            points: vec![
-                LinePoint {
-                    p: canvas.point_center_left_bottom,
-                    bezier: false,
-                },
-                LinePoint {
-                    p: canvas.point_center_left_top,
-                    bezier: false,
-                },
-                LinePoint {
-                    p: canvas.point_center_right_top,
-                    bezier: false,
-                },
-                LinePoint {
-                    p: canvas.point_center_right_bottom,
-                    bezier: false,
-                },
-            ],
-            is_closed: true,
-        } } ); 
-    
+    // 绘制页面内边框（细线），只绘制外扩的边
+    ops.push(Op::SetOutlineThickness { pt: canvas.thin_line_width_pt });
+    if canvas.frame_visible && canvas.frame_offset_y_pt.0 > 0.0 {
+        ops.extend(add_line(jitter, &[canvas.point_left_top, canvas.point_right_top], false));
+        ops.extend(add_line(jitter, &[canvas.point_left_bottom, canvas.point_right_bottom], false));
+    }
+    if canvas.frame_visible && canvas.frame_offset_x_pt.0 > 0.0 {
+        ops.extend(add_line(jitter, &[canvas.point_left_bottom, canvas.point_left_top], false));
+        ops.extend(add_line(jitter, &[canvas.point_right_bottom, canvas.point_right_top], false));
+    }
+    ops.extend(add_line(jitter, &[
+        canvas.point_center_left_bottom,
+        canvas.point_center_left_top,
+        canvas.point_center_right_top,
+        canvas.point_center_right_bottom,
+    ], true));
 
     //print!("绘制列线\n");
-    for i in 0..column_count{
+    // 无界时不绘制列线
+    let rule_count = if canvas.column_rules { column_count } else { 0 };
+    for i in 0..rule_count{
         if i < column_count /2 || direction ==1 {
-            ops.push(Op::DrawLine { 
-                line: Line {
-                    points: vec![
-                        LinePoint {
-                            p: Point {
-                                x: canvas.point_left_bottom.x 
-                                   + canvas.column_width_pt * i as f32,
-                                y: canvas.point_left_bottom.y, // Bottom left
-                            },
-                            bezier: false,
-                        },
-                        LinePoint {
-                            p: Point {
-                                x: canvas.point_left_top.x + canvas.column_width_pt * i as f32,
-                                y: canvas.point_left_top.y, // Top left
-                            },
-                            bezier: false,
-                        },
-                    ],
-                    is_closed: false,
-                } } ); 
-        }else{
-            ops.push(Op::DrawLine { 
-                line: Line {
-                    points: vec![
-                        LinePoint {
-                            p: Point {
-                                x: canvas.point_right_bottom.x 
-                                   - canvas.column_width_pt 
-                                   * (i - column_count / 2) as f32,
-                                y: canvas.point_right_bottom.y, // Bottom left
-                            },
-                            bezier: false,
-                        },
-                        LinePoint {
-                            p: Point {
-                                x: canvas.point_right_top.x 
-                                   - canvas.column_width_pt 
-                                   * (i - column_count / 2) as f32,
-                                y: canvas.point_right_top.y, // Top left
-                            },
-                            bezier: false,
-                        },
-                    ],
-                    is_closed: false,
-                } } ); 
-        }
-    }
-    //绘制鱼尾上下细线
-    // 下鱼尾
-    ops.push(Op::DrawLine { 
-        line: Line {
-            points: vec![
-                LinePoint {
-                    p: tail.point_line_down_left,
-                    bezier: false,
+            ops.extend(add_line(jitter, &[
+                Point {
+                    x: canvas.point_left_bottom.x 
+                       + canvas.column_width_pt * i as f32,
+                    y: canvas.point_left_bottom.y, // Bottom left
                 },
-                LinePoint {
-                    p: tail.point_line_down_right,
-                    bezier: false,
+                Point {
+                    x: canvas.point_left_top.x + canvas.column_width_pt * i as f32,
+                    y: canvas.point_left_top.y, // Top left
                 },
-            ],
-            is_closed: false,
-        } } ); 
-    ops.push(Op::DrawLine { 
-        line: Line {
-            points: vec![
-                LinePoint {
-                    p: tail.point_line_up_left,
-                    bezier: false,
+            ], false));
+        }else{
+            ops.extend(add_line(jitter, &[
+                Point {
+                    x: canvas.point_right_bottom.x 
+                       - canvas.column_width_pt 
+                       * (i - column_count / 2) as f32,
+                    y: canvas.point_right_bottom.y, // Bottom left
                 },
-                LinePoint {
-                    p: tail.point_line_up_right,
-                    bezier: false,
+                Point {
+                    x: canvas.point_right_top.x 
+                       - canvas.column_width_pt 
+                       * (i - column_count / 2) as f32,
+                    y: canvas.point_right_top.y, // Top left
                 },
-            ],
-            is_closed: false,
-        } } );
+            ], false));
+        }
+    }
+    //绘制书耳
+    if ear.visible {
+        ops.extend(add_line(jitter, &[
+            ear.point_left_bottom,
+            Point { x: ear.point_left_bottom.x, y: ear.point_right_top.y },
+            ear.point_right_top,
+            Point { x: ear.point_right_top.x, y: ear.point_left_bottom.y },
+        ], true));
+    }
+    //绘制鱼尾上下细线
+    ops.extend(add_line(jitter, &[tail.point_line_up_left, tail.point_line_up_right], false));
+    if tail.double {
+        ops.extend(add_line(jitter, &[tail.point_line_down_left, tail.point_line_down_right], false));
+    }
 
     //绘制鱼尾
     // 上鱼尾
-    ops.push(Op::SetFillColor { col: line_color.clone()});
-    ops.push(Op::DrawPolygon {
-        polygon: printpdf::Polygon {
-            rings: vec![PolygonRing {
-                points: vec![
-                    LinePoint {
-                        p: tail.point_up_left_top,
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: tail.point_up_right_top,  
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: tail.point_up_right_bottom,
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: tail.point_up_center,
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: tail.point_up_left_bottom,
-                        bezier: false,
-                    },
-                ],
-            }],
-            mode: printpdf::PaintMode::Fill,
-            winding_order: printpdf::WindingOrder::NonZero,
-        },
-    }); 
+    let up_tail = [
+        tail.point_up_left_top,
+        tail.point_up_right_top,
+        tail.point_up_right_bottom,
+        tail.point_up_center,
+        tail.point_up_left_bottom,
+    ];
+    ops.append(&mut add_tail(&up_tail, tail, &line_color, &bg_color, jitter));
     // 下鱼尾
-    ops.push(Op::DrawPolygon {
-        polygon: printpdf::Polygon {
-            rings: vec![PolygonRing {
-                points: vec![
-                    LinePoint {
-                        p: tail.point_down_left_bottom,
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: tail.point_down_right_bottom,  
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: tail.point_down_right_top,
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: tail.point_down_center,
-                        bezier: false,
-                    },
-                    LinePoint {
-                        p: tail.point_down_left_top,
-                        bezier: false,
-                    },
-                ],
-            }],
-            mode: printpdf::PaintMode::Fill,
-            winding_order: printpdf::WindingOrder::NonZero,
-        },
-    });   
+    if tail.double {
+        let down_tail = if tail.down_flipped {
+            [
+                tail.point_down_left_top,
+                tail.point_down_right_top,
+                tail.point_down_right_bottom,
+                tail.point_down_center,
+                tail.point_down_left_bottom,
+            ]
+        } else {
+            [
+                tail.point_down_left_bottom,
+                tail.point_down_right_bottom,
+                tail.point_down_right_top,
+                tail.point_down_center,
+                tail.point_down_left_top,
+            ]
+        };
+        ops.append(&mut add_tail(&down_tail, tail, &line_color, &bg_color, jitter));
+    }
     ops.push(Op::EndLayer {layer_id: bg_layer_id.clone()});  
     ops
 }
 
-
+// 绘制单个鱼尾，points 依次为平边两点、缺口侧两端点与缺口中心
+fn add_tail(points: &[Point; 5],
+            tail: &Tail,
+            line_color: &Color,
+            bg_color: &Color,
+            jitter: &mut Jitter) -> Vec<Op> {
+    let mut ops = vec![];
+    let polygon = |pts: &[Point]| printpdf::Polygon {
+        rings: vec![PolygonRing {
+            points: pts.iter().map(|p| LinePoint { p: *p, bezier: false }).collect(),
+        }],
+        mode: printpdf::PaintMode::Fill,
+        winding_order: printpdf::WindingOrder::NonZero,
+    };
+    match tail.fill_style.as_str() {
+        "白鱼尾" => {
+            // 白鱼尾：仅绘制轮廓
+            ops.extend(add_line(jitter, points, true));
+        }
+        "花鱼尾" => {
+            // 花鱼尾：黑鱼尾内以底色勾出花纹
+            ops.push(Op::SetFillColor { col: line_color.clone() });
+            ops.push(Op::DrawPolygon { polygon: polygon(points) });
+            let cx = points.iter().map(|p| p.x.0).sum::<f32>() / 5.0;
+            let cy = points.iter().map(|p| p.y.0).sum::<f32>() / 5.0;
+            let inset: Vec<Point> = points.iter()
+                .map(|p| Point {
+                    x: Pt(cx + (p.x.0 - cx) * 0.6),
+                    y: Pt(cy + (p.y.0 - cy) * 0.6),
+                })
+                .collect();
+            ops.push(Op::SetOutlineColor { col: bg_color.clone() });
+            ops.extend(add_line(jitter, &inset, true));
+            // 花纹：从平边中点至缺口中心的竖线
+            let flat_mid = Point {
+                x: (inset[0].x + inset[1].x) / 2.0,
+                y: (inset[0].y + inset[1].y) / 2.0,
+            };
+            ops.extend(add_line(jitter, &[flat_mid, inset[3]], false));
+            ops.push(Op::SetOutlineColor { col: line_color.clone() });
+        }
+        _ => {
+            // 黑鱼尾
+            ops.push(Op::SetFillColor { col: line_color.clone() });
+            ops.push(Op::DrawPolygon { polygon: polygon(points) });
+        }
+    }
+    ops
+}
@@ -0,0 +1,149 @@
+// 位图导出：在 CPU 上按各页的 PDF 绘制指令逐页栅格化，版框、鱼尾、文字位置与 PDF 完全一致
+use ab_glyph::FontVec;
+use printpdf::*;
+use crate::config::*;
+use crate::pdfoption::PagedBook;
+use crate::render::{self, Canvas, ImagePlacement, PathPaint, PathSink, PlacedGlyph, Resources, State};
+use crate::utils;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, PixmapPaint, Stroke, Transform};
+
+/// 逐页输出 PNG，文件名为 {书名}_{页码:04}.png，放在 PDF 输出路径所在目录，返回页数
+/// dpi 限制在 IMAGE_DPI_MIN 到 IMAGE_DPI_MAX 之间
+pub fn export_png(book: &PagedBook, dpi: u32, output_dir: &Path) -> Result<usize, Box<dyn Error>> {
+    std::fs::create_dir_all(output_dir)?;
+    let resources = Resources::from_book(book, |png| Pixmap::decode_png(png).ok());
+    let dpi = dpi.clamp(IMAGE_DPI_MIN, IMAGE_DPI_MAX) as f32;
+    let name = utils::file_name_safe(&book.name);
+    // 每个线程各占一整页位图，按内存上限限制同时渲染的页数
+    let page_mb = (book.page_width_mm / MM_PER_INCH * dpi) * (book.page_height_mm / MM_PER_INCH * dpi) * 4.0 / 1048576.0;
+    let threads = ((PNG_MEMORY_MB / page_mb.max(1.0)) as usize).clamp(1, rayon::current_num_threads());
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build()?;
+    pool.install(|| {
+        book.pages
+            .par_iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let pixmap = render_page(&page.ops, book.page_width_mm, book.page_height_mm, dpi, &resources)
+                    .ok_or("页面尺寸无效")?;
+                let path = output_dir.join(format!("{}_{:04}.png", name, i + 1));
+                pixmap.save_png(&path).map_err(|e| format!("保存{}失败: {}", path.display(), e))?;
+                Ok(())
+            })
+            .collect::<Result<Vec<()>, String>>()
+    })?;
+    Ok(book.pages.len())
+}
+
+/// 按绘制指令栅格化一页
+pub fn render_page(ops: &[Op], width_mm: f32, height_mm: f32, dpi: f32, resources: &Resources<Pixmap>) -> Option<Pixmap> {
+    let scale = dpi / PT_PER_INCH;
+    let width_px = (width_mm / MM_PER_INCH * dpi).round() as u32;
+    let height_px = (height_mm / MM_PER_INCH * dpi).round() as u32;
+    let mut pixmap = Pixmap::new(width_px, height_px)?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    let mut canvas = Raster {
+        pixmap: &mut pixmap,
+        // PDF 坐标 y 轴向上，位图 y 轴向下
+        page: Transform::from_row(scale, 0.0, 0.0, -scale, 0.0, height_px as f32),
+        glyph_cache: HashMap::new(),
+    };
+    // 图层只用于 PDF 阅读器中切换显示，位图中全部绘出
+    render::render_page(ops, resources, &mut canvas);
+    Some(pixmap)
+}
+
+struct Raster<'a> {
+    pixmap: &'a mut Pixmap,
+    page: Transform,
+    glyph_cache: HashMap<(FontId, u16), Option<tiny_skia::Path>>,
+}
+
+impl PathSink for PathBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        PathBuilder::move_to(self, x, y);
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        PathBuilder::line_to(self, x, y);
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        PathBuilder::quad_to(self, x1, y1, x, y);
+    }
+    fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        PathBuilder::cubic_to(self, x1, y1, x2, y2, x, y);
+    }
+    fn close(&mut self) {
+        PathBuilder::close(self);
+    }
+}
+
+impl Canvas for Raster<'_> {
+    type Image = Pixmap;
+
+    fn draw_path(&mut self, rings: &[(&[LinePoint], bool)], paint: PathPaint, state: &State) {
+        let mut builder = PathBuilder::new();
+        for (points, is_closed) in rings {
+            render::append_points(&mut builder, points, *is_closed);
+        }
+        let Some(path) = builder.finish() else {
+            return;
+        };
+        if paint.fill {
+            let rule = if paint.even_odd { FillRule::EvenOdd } else { FillRule::Winding };
+            self.pixmap.fill_path(&path, &skia_paint(&state.fill), rule, self.page, None);
+        }
+        if paint.stroke {
+            let stroke = Stroke { width: state.line_width, ..Stroke::default() };
+            self.pixmap.stroke_path(&path, &skia_paint(&state.stroke), &stroke, self.page, None);
+        }
+    }
+
+    fn draw_glyphs(&mut self, font: &FontId, face: &FontVec, glyphs: &[PlacedGlyph], state: &State) {
+        let scale = state.glyph_scale(face);
+        let paint = skia_paint(&state.fill);
+        for glyph in glyphs {
+            let path = self.glyph_cache.entry((font.clone(), glyph.id)).or_insert_with(|| {
+                let mut builder = PathBuilder::new();
+                render::append_outline(&mut builder, face, glyph.id);
+                builder.finish()
+            });
+            if let Some(path) = path {
+                let (x, y) = state.text_position(glyph.advance);
+                let transform = Transform::from_scale(scale, scale)
+                    .post_rotate(state.text_angle)
+                    .post_translate(x, y)
+                    .post_concat(self.page);
+                self.pixmap.fill_path(path, &paint, FillRule::Winding, transform, None);
+            }
+        }
+    }
+
+    fn draw_image(&mut self, image: &Pixmap, placement: &ImagePlacement) {
+        // 图像第一行在上方
+        let transform = Transform::from_row(
+            placement.pt_per_px_x,
+            0.0,
+            0.0,
+            -placement.pt_per_px_y,
+            placement.x,
+            placement.y + image.height() as f32 * placement.pt_per_px_y,
+        )
+        .post_concat(self.page);
+        self.pixmap.draw_pixmap(0, 0, image.as_ref(), &PixmapPaint::default(), transform, None);
+    }
+}
+
+fn skia_paint(color: &Color) -> Paint<'static> {
+    let color = match color {
+        Color::Rgb(rgb) => tiny_skia::Color::from_rgba(rgb.r.clamp(0.0, 1.0), rgb.g.clamp(0.0, 1.0), rgb.b.clamp(0.0, 1.0), 1.0)
+            .unwrap_or(tiny_skia::Color::BLACK),
+        _ => tiny_skia::Color::BLACK,
+    };
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = true;
+    paint
+}
@@ -0,0 +1,251 @@
+// 页面绘制指令的解释：PNG、SVG 导出共用，逐条读取 PDF 绘制指令，维护颜色、线宽和文字矩阵，
+// 算出每个字形、图像的位置后交给各导出后端绘出
+use ab_glyph::{Font, FontVec, GlyphId, OutlineCurve};
+use printpdf::*;
+use crate::config::*;
+use crate::pdfoption::PagedBook;
+use std::collections::HashMap;
+
+/// 导出所需的字体和图像，按 PDF 中的编号查找；图像由各后端按需解码
+pub struct Resources<I> {
+    pub fonts: HashMap<FontId, FontVec>,
+    pub images: HashMap<XObjectId, I>,
+}
+
+impl<I> Resources<I> {
+    pub fn from_book(book: &PagedBook, decode: impl Fn(&'static [u8]) -> Option<I>) -> Self {
+        let fonts = book
+            .fonts
+            .iter()
+            .filter_map(|(id, bytes, index)| {
+                FontVec::try_from_vec_and_index(bytes.clone(), *index as u32)
+                    .or_else(|_| FontVec::try_from_vec(bytes.clone()))
+                    .ok()
+                    .map(|font| (id.clone(), font))
+            })
+            .collect();
+        let images = book
+            .images
+            .iter()
+            .filter_map(|(id, png)| decode(png).map(|image| (id.clone(), image)))
+            .collect();
+        Resources { fonts, images }
+    }
+}
+
+/// 绘图状态：颜色、线宽、当前字号和文字矩阵
+pub struct State {
+    pub fill: Color,
+    pub stroke: Color,
+    pub line_width: f32,
+    pub font_size: f32,
+    pub text_origin: (f32, f32),
+    pub text_angle: f32,
+    text_advance: f32, // 文字矩阵设置后已写出的宽度（点）
+}
+
+impl State {
+    /// 字体单位到点的缩放比例
+    pub fn glyph_scale(&self, face: &FontVec) -> f32 {
+        self.font_size / face.units_per_em().unwrap_or(1000.0)
+    }
+
+    /// 沿文字方向距文字矩阵原点 advance 点处的页面坐标
+    pub fn text_position(&self, advance: f32) -> (f32, f32) {
+        let (sin, cos) = self.text_angle.to_radians().sin_cos();
+        (self.text_origin.0 + advance * cos, self.text_origin.1 + advance * sin)
+    }
+}
+
+/// 一个待绘的字形：advance 为其起点沿文字方向距文字矩阵原点的宽度（点）
+pub struct PlacedGlyph {
+    pub id: u16,
+    pub c: char,
+    pub advance: f32,
+}
+
+/// 路径的绘制方式
+pub struct PathPaint {
+    pub fill: bool,
+    pub stroke: bool,
+    pub even_odd: bool,
+}
+
+/// 图像按 XObject 变换放置：左下角在 (x, y)，每像素的点数按 dpi 折算后再缩放
+pub struct ImagePlacement {
+    pub x: f32,
+    pub y: f32,
+    pub pt_per_px_x: f32,
+    pub pt_per_px_y: f32,
+}
+
+impl ImagePlacement {
+    fn from_transform(transform: &XObjectTransform) -> Self {
+        let dpi = transform.dpi.unwrap_or(300.0);
+        ImagePlacement {
+            x: transform.translate_x.map(|pt| pt.0).unwrap_or(0.0),
+            y: transform.translate_y.map(|pt| pt.0).unwrap_or(0.0),
+            pt_per_px_x: PT_PER_INCH / dpi * transform.scale_x.unwrap_or(1.0),
+            pt_per_px_y: PT_PER_INCH / dpi * transform.scale_y.unwrap_or(1.0),
+        }
+    }
+}
+
+/// 导出后端：坐标均为 PDF 的点，原点在左下角
+pub trait Canvas {
+    type Image;
+
+    fn begin_layer(&mut self, _layer_id: &LayerInternalId) {}
+    fn end_layer(&mut self) {}
+    /// rings 中每项为一段折线及其是否闭合
+    fn draw_path(&mut self, rings: &[(&[LinePoint], bool)], paint: PathPaint, state: &State);
+    fn draw_glyphs(&mut self, font: &FontId, face: &FontVec, glyphs: &[PlacedGlyph], state: &State);
+    fn draw_image(&mut self, image: &Self::Image, placement: &ImagePlacement);
+}
+
+/// 路径的接收方，PNG 为位图路径，SVG 为路径数据
+pub trait PathSink {
+    fn move_to(&mut self, x: f32, y: f32);
+    fn line_to(&mut self, x: f32, y: f32);
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32);
+    fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32);
+    fn close(&mut self);
+}
+
+/// 按绘制指令绘出一页
+pub fn render_page<C: Canvas>(ops: &[Op], resources: &Resources<C::Image>, canvas: &mut C) {
+    let black = Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None));
+    let mut state = State {
+        fill: black.clone(),
+        stroke: black,
+        line_width: 1.0,
+        font_size: 12.0,
+        text_origin: (0.0, 0.0),
+        text_angle: 0.0,
+        text_advance: 0.0,
+    };
+    for op in ops {
+        match op {
+            Op::BeginLayer { layer_id } => canvas.begin_layer(layer_id),
+            Op::EndLayer { .. } => canvas.end_layer(),
+            Op::SetFillColor { col } => state.fill = col.clone(),
+            Op::SetOutlineColor { col } => state.stroke = col.clone(),
+            Op::SetOutlineThickness { pt } => state.line_width = pt.0,
+            Op::SetFontSize { size, .. } => state.font_size = size.0,
+            Op::SetTextMatrix { matrix: TextMatrix::TranslateRotate(x, y, angle) } => {
+                state.text_origin = (x.0, y.0);
+                state.text_angle = *angle;
+                state.text_advance = 0.0;
+            }
+            Op::DrawLine { line } => {
+                let paint = PathPaint { fill: false, stroke: true, even_odd: false };
+                canvas.draw_path(&[(&line.points, line.is_closed)], paint, &state);
+            }
+            Op::DrawPolygon { polygon } => {
+                let (fill, stroke) = match polygon.mode {
+                    PaintMode::Fill => (true, false),
+                    PaintMode::Stroke => (false, true),
+                    PaintMode::FillStroke => (true, true),
+                    _ => continue,
+                };
+                let even_odd = matches!(polygon.winding_order, WindingOrder::EvenOdd);
+                let rings: Vec<(&[LinePoint], bool)> = polygon.rings.iter().map(|ring| (ring.points.as_slice(), true)).collect();
+                canvas.draw_path(&rings, PathPaint { fill, stroke, even_odd }, &state);
+            }
+            Op::WriteText { items, font } => {
+                let Some(face) = resources.fonts.get(font) else {
+                    continue;
+                };
+                for item in items {
+                    match item {
+                        TextItem::Text(text) => {
+                            let glyphs: Vec<(u16, char)> = text.chars().map(|c| (face.glyph_id(c).0, c)).collect();
+                            write_glyphs(canvas, font, face, &glyphs, &mut state);
+                        }
+                        // 字距调整以千分之一字号为单位，正值向左收紧
+                        TextItem::Offset(offset) => state.text_advance -= offset / 1000.0 * state.font_size,
+                    }
+                }
+            }
+            Op::WriteCodepoints { font, cp } => {
+                if let Some(face) = resources.fonts.get(font) {
+                    write_glyphs(canvas, font, face, cp, &mut state);
+                }
+            }
+            Op::UseXobject { id, transform } => {
+                if let Some(image) = resources.images.get(id) {
+                    canvas.draw_image(image, &ImagePlacement::from_transform(transform));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// 字形沿文字方向依次排开，写完后文字矩阵的当前位置前进到末尾
+fn write_glyphs<C: Canvas>(canvas: &mut C, font: &FontId, face: &FontVec, glyphs: &[(u16, char)], state: &mut State) {
+    let scale = state.glyph_scale(face);
+    let placed: Vec<PlacedGlyph> = glyphs
+        .iter()
+        .map(|(id, c)| {
+            let glyph = PlacedGlyph { id: *id, c: *c, advance: state.text_advance };
+            state.text_advance += face.h_advance_unscaled(GlyphId(*id)) * scale;
+            glyph
+        })
+        .collect();
+    canvas.draw_glyphs(font, face, &placed, state);
+}
+
+/// 折线写入路径，bezier 标记的点为三次贝塞尔曲线的控制点
+pub fn append_points(sink: &mut impl PathSink, points: &[LinePoint], is_closed: bool) {
+    let Some(first) = points.first() else {
+        return;
+    };
+    sink.move_to(first.p.x.0, first.p.y.0);
+    let mut i = 1;
+    while i < points.len() {
+        if points[i].bezier && i + 2 < points.len() {
+            let (c1, c2, end) = (points[i].p, points[i + 1].p, points[i + 2].p);
+            sink.cubic_to(c1.x.0, c1.y.0, c2.x.0, c2.y.0, end.x.0, end.y.0);
+            i += 3;
+        } else {
+            sink.line_to(points[i].p.x.0, points[i].p.y.0);
+            i += 1;
+        }
+    }
+    if is_closed {
+        sink.close();
+    }
+}
+
+/// 字形轮廓写入路径，坐标为字体单位，y 轴向上；字形没有轮廓时返回 false
+pub fn append_outline(sink: &mut impl PathSink, face: &FontVec, glyph_id: u16) -> bool {
+    let Some(outline) = face.outline(GlyphId(glyph_id)) else {
+        return false;
+    };
+    let mut last = None;
+    for curve in &outline.curves {
+        let (start, end) = match curve {
+            OutlineCurve::Line(p0, p1) => (*p0, *p1),
+            OutlineCurve::Quad(p0, _, p2) => (*p0, *p2),
+            OutlineCurve::Cubic(p0, _, _, p3) => (*p0, *p3),
+        };
+        // 起点与上一段终点不接时为新轮廓
+        if last != Some(start) {
+            if last.is_some() {
+                sink.close();
+            }
+            sink.move_to(start.x, start.y);
+        }
+        match curve {
+            OutlineCurve::Line(_, p1) => sink.line_to(p1.x, p1.y),
+            OutlineCurve::Quad(_, p1, p2) => sink.quad_to(p1.x, p1.y, p2.x, p2.y),
+            OutlineCurve::Cubic(_, p1, p2, p3) => sink.cubic_to(p1.x, p1.y, p2.x, p2.y, p3.x, p3.y),
+        }
+        last = Some(end);
+    }
+    if last.is_some() {
+        sink.close();
+    }
+    last.is_some()
+}
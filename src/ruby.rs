@@ -0,0 +1,95 @@
+// 注音标记：{字|zì}、{中国|zhōng guó}，注音以小字排在正文右侧，不占正文字位
+
+pub const RUBY_OPEN: char = '{';
+pub const RUBY_SEPARATOR: char = '|';
+pub const RUBY_CLOSE: char = '}';
+// 标记内正文与注音的最大字符数，正文只有几个字，注音为其拼音
+const RUBY_MAX_LEN: usize = 64;
+
+/// 一段注音：正文中 start..end 的字符共用一条注音
+pub struct RubySpan {
+    pub start: usize,
+    pub end: usize,
+    pub gloss: String,
+}
+
+/// 解析 chars 开头的注音标记，返回正文、注音和标记长度
+pub fn parse_ruby(chars: &[char]) -> Option<(String, String, usize)> {
+    if chars.first() != Some(&RUBY_OPEN) {
+        return None;
+    }
+    // 只在同一段内、限定长度里找结束符，正文中单独的 { 不会吞掉后文
+    let close = 1 + chars[1..]
+        .iter()
+        .take(RUBY_MAX_LEN + 1)
+        .take_while(|c| **c != '\n')
+        .position(|c| *c == RUBY_CLOSE)?;
+    let inner = &chars[1..close];
+    let separator = inner.iter().position(|c| *c == RUBY_SEPARATOR)?;
+    let base: String = inner[..separator].iter().collect();
+    let gloss: String = inner[separator + 1..].iter().collect();
+    if base.is_empty() || gloss.is_empty() || base.contains(RUBY_OPEN) {
+        return None;
+    }
+    Some((base, gloss, close + 1))
+}
+
+/// 去掉注音标记，返回正文字符和各段注音的位置
+pub fn strip_ruby(text: &str) -> (Vec<char>, Vec<RubySpan>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut base_chars = Vec::with_capacity(chars.len());
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some((base, gloss, len)) = parse_ruby(&chars[i..]) {
+            let start = base_chars.len();
+            base_chars.extend(base.chars());
+            spans.push(RubySpan { start, end: base_chars.len(), gloss });
+            i += len;
+        } else {
+            base_chars.push(chars[i]);
+            i += 1;
+        }
+    }
+    (base_chars, spans)
+}
+
+/// 只保留正文，用于书耳等不排注音的位置
+pub fn strip_markup(text: &str) -> String {
+    strip_ruby(text).0.into_iter().collect()
+}
+
+/// 注音是否按拉丁文字旋转排列（拼音等），否则逐字直排（注音符号、反切）
+pub fn is_latin_gloss(gloss: &str) -> bool {
+    gloss.chars().all(|c| c.is_whitespace() || ((c.is_alphanumeric() || c.is_ascii_punctuation()) && (c as u32) < 0x2E80))
+}
+
+/// 正文跨行拆开时拆分注音：音节数与字数相同时按字分配，否则整条注音随第一段
+pub fn split_gloss(gloss: &str, lengths: &[usize]) -> Vec<String> {
+    let syllables: Vec<&str> = gloss.split_whitespace().collect();
+    if syllables.len() == lengths.iter().sum::<usize>() {
+        let mut start = 0;
+        return lengths
+            .iter()
+            .map(|len| {
+                let part = syllables[start..start + len].join(" ");
+                start += len;
+                part
+            })
+            .collect();
+    }
+    lengths
+        .iter()
+        .enumerate()
+        .map(|(i, _)| if i == 0 { gloss.to_string() } else { String::new() })
+        .collect()
+}
+
+/// 生成注音标记，注音为空时只输出正文
+pub fn markup(base: &str, gloss: &str) -> String {
+    if gloss.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}{}{}{}{}", RUBY_OPEN, base, RUBY_SEPARATOR, gloss, RUBY_CLOSE)
+    }
+}
@@ -0,0 +1,233 @@
+// SVG 导出：按各页的 PDF 绘制指令逐页写出独立的 SVG，供矢量软件调整版面
+// 框线为 <path>，文字为定位的 <text> 或字形轮廓，背景层与文字层分组，与 PDF 的可选内容图层对应
+use ab_glyph::{Font, FontVec};
+use printpdf::*;
+use crate::config::*;
+use crate::metrics;
+use crate::pdfoption::PagedBook;
+use crate::render::{self, Canvas, ImagePlacement, PathPaint, PathSink, PlacedGlyph, Resources, State};
+use crate::utils::{self, escape_xml};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// 逐页输出 SVG，文件名为 {书名}_{页码:04}.svg，outline 为真时文字转为字形轮廓，返回页数
+pub fn export_svg(book: &PagedBook, outline: bool, output_dir: &Path) -> Result<usize, Box<dyn Error>> {
+    std::fs::create_dir_all(output_dir)?;
+    // 图像保留 PNG 原数据，以 data URI 嵌入
+    let resources = Resources::from_book(book, |png| png_size(png).map(|(width, height)| (png, width, height)));
+    let families: HashMap<FontId, String> = book
+        .fonts
+        .iter()
+        .map(|(id, bytes, index)| {
+            let family = metrics::font_family_name(bytes, *index as u32).unwrap_or_else(|| "serif".to_string());
+            (id.clone(), family)
+        })
+        .collect();
+    let name = utils::file_name_safe(&book.name);
+    book.pages
+        .par_iter()
+        .enumerate()
+        .map(|(i, page)| {
+            let mut canvas = Svg {
+                svg: String::new(),
+                outline,
+                layers: &book.layers,
+                families: &families,
+                glyph_cache: HashMap::new(),
+            };
+            canvas.render_page(&page.ops, book.page_width_mm, book.page_height_mm, &resources);
+            let path = output_dir.join(format!("{}_{:04}.svg", name, i + 1));
+            std::fs::write(&path, canvas.svg).map_err(|e| format!("保存{}失败: {}", path.display(), e))
+        })
+        .collect::<Result<Vec<()>, String>>()?;
+    Ok(book.pages.len())
+}
+
+struct Svg<'a> {
+    svg: String,
+    outline: bool,
+    layers: &'a [(LayerInternalId, String)],
+    families: &'a HashMap<FontId, String>,
+    glyph_cache: HashMap<(FontId, u16), String>,
+}
+
+impl Svg<'_> {
+    /// 一页的 SVG；坐标为 PDF 的点，整页翻转 y 轴使原点在左下角
+    fn render_page(&mut self, ops: &[Op], width_mm: f32, height_mm: f32, resources: &Resources<(&'static [u8], u32, u32)>) {
+        let width_pt = width_mm * MM_TO_PT;
+        let height_pt = height_mm * MM_TO_PT;
+        let _ = writeln!(self.svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            self.svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" width="{}mm" height="{}mm" viewBox="0 0 {} {}">"#,
+            width_mm, height_mm, width_pt, height_pt
+        );
+        let _ = writeln!(self.svg, r#"<g transform="matrix(1 0 0 -1 0 {})">"#, height_pt);
+        render::render_page(ops, resources, self);
+        self.svg.push_str("</g>\n</svg>\n");
+    }
+
+    // 一个字形的轮廓 <path>，路径数据按字体单位缓存
+    fn write_outline(&mut self, font: &FontId, face: &FontVec, glyph: &PlacedGlyph, state: &State) {
+        let data = self.glyph_cache.entry((font.clone(), glyph.id)).or_insert_with(|| {
+            let mut data = String::new();
+            render::append_outline(&mut data, face, glyph.id);
+            data
+        });
+        if !data.is_empty() {
+            let (x, y) = state.text_origin;
+            let _ = writeln!(
+                self.svg,
+                r#"<path transform="translate({} {}) rotate({}) translate({} 0) scale({})" d="{}" fill="{}"/>"#,
+                x,
+                y,
+                state.text_angle,
+                glyph.advance,
+                state.glyph_scale(face),
+                data,
+                svg_color(&state.fill)
+            );
+        }
+    }
+}
+
+// 路径数据
+impl PathSink for String {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let _ = write!(self, "M{} {}", x, y);
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        let _ = write!(self, "L{} {}", x, y);
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let _ = write!(self, "Q{} {} {} {}", x1, y1, x, y);
+    }
+    fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let _ = write!(self, "C{} {} {} {} {} {}", x1, y1, x2, y2, x, y);
+    }
+    fn close(&mut self) {
+        self.push('Z');
+    }
+}
+
+impl Canvas for Svg<'_> {
+    type Image = (&'static [u8], u32, u32); // PNG 数据及像素宽高
+
+    fn begin_layer(&mut self, layer_id: &LayerInternalId) {
+        // 图层名可含空格，id 按图层序号生成
+        let index = self.layers.iter().position(|(id, _)| id == layer_id).unwrap_or(self.layers.len());
+        let name = self.layers.get(index).map(|(_, name)| name.as_str()).unwrap_or_default();
+        let _ = writeln!(
+            self.svg,
+            r#"<g id="layer{}" inkscape:groupmode="layer" inkscape:label="{}">"#,
+            index + 1,
+            escape_xml(name)
+        );
+    }
+
+    fn end_layer(&mut self) {
+        self.svg.push_str("</g>\n");
+    }
+
+    fn draw_path(&mut self, rings: &[(&[LinePoint], bool)], paint: PathPaint, state: &State) {
+        let mut data = String::new();
+        for (points, is_closed) in rings {
+            render::append_points(&mut data, points, *is_closed);
+        }
+        let mut attributes = String::new();
+        if paint.fill {
+            let rule = if paint.even_odd { "evenodd" } else { "nonzero" };
+            let _ = write!(attributes, r#"fill="{}" fill-rule="{}""#, svg_color(&state.fill), rule);
+        } else {
+            attributes.push_str(r#"fill="none""#);
+        }
+        if paint.stroke {
+            let _ = write!(attributes, r#" stroke="{}" stroke-width="{}""#, svg_color(&state.stroke), state.line_width);
+        }
+        let _ = writeln!(self.svg, r#"<path d="{}" {}/>"#, data, attributes);
+    }
+
+    // 文字模式下连续的字形合为一个 <text>；竖排标点等经 GSUB 替换的字形与字符的默认字形不同，
+    // 写成文字会显示为横排形式，改为轮廓 <path>
+    fn draw_glyphs(&mut self, font: &FontId, face: &FontVec, glyphs: &[PlacedGlyph], state: &State) {
+        let mut start = 0;
+        while start < glyphs.len() {
+            let substituted = |glyph: &PlacedGlyph| face.glyph_id(glyph.c).0 != glyph.id;
+            if self.outline || substituted(&glyphs[start]) {
+                self.write_outline(font, face, &glyphs[start], state);
+                start += 1;
+                continue;
+            }
+            let end = glyphs[start..].iter().position(substituted).map_or(glyphs.len(), |n| start + n);
+            let text: String = glyphs[start..end].iter().map(|glyph| glyph.c).collect();
+            let family = self.families.get(font).map(String::as_str).unwrap_or("serif");
+            let (x, y) = state.text_origin;
+            // 文字在翻转后的坐标中再翻转一次，字形才是正的
+            let _ = writeln!(
+                self.svg,
+                r#"<text transform="translate({} {}) rotate({}) scale(1 -1)" x="{}" y="0" font-family="{}" font-size="{}" fill="{}">{}</text>"#,
+                x,
+                y,
+                state.text_angle,
+                glyphs[start].advance,
+                escape_xml(family),
+                state.font_size,
+                svg_color(&state.fill),
+                escape_xml(&text)
+            );
+            start = end;
+        }
+    }
+
+    fn draw_image(&mut self, &(png, width_px, height_px): &Self::Image, placement: &ImagePlacement) {
+        let width = width_px as f32 * placement.pt_per_px_x;
+        let height = height_px as f32 * placement.pt_per_px_y;
+        let _ = writeln!(
+            self.svg,
+            r#"<image transform="translate({} {}) scale(1 -1)" width="{}" height="{}" preserveAspectRatio="none" xlink:href="data:image/png;base64,{}"/>"#,
+            placement.x,
+            placement.y + height,
+            width,
+            height,
+            base64(png)
+        );
+    }
+}
+
+fn svg_color(color: &Color) -> String {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match color {
+        Color::Rgb(rgb) => format!("#{:02x}{:02x}{:02x}", channel(rgb.r), channel(rgb.g), channel(rgb.b)),
+        _ => "#000000".to_string(),
+    }
+}
+
+// PNG 的像素宽高，取自 IHDR
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    if png.get(0..8)? != b"\x89PNG\r\n\x1a\n" || png.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(png.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let value = ((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(TABLE[((value >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
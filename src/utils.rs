@@ -5,6 +5,57 @@ use encoding_rs::{UTF_8, GBK, UTF_16LE, UTF_16BE};
 use std::fs::File;
 use std::io::{Read};
 use std::error::Error;
+use crate::config::{FileInfo, Typeset, MAX_RAISE, NOTE_SCALE};
+use crate::convert::Converter;
+use crate::ruby;
+use crate::numeral;
+use crate::epub;
+use crate::document::{Block, Document};
+
+/// 读取输入文件并解析为文档树，完成简繁转换和数字转换
+/// EPUB 按书脊顺序转为 %% 分章的文本，未填书名、作者时取其元数据
+pub fn load_document(fileinfo: &mut FileInfo, typeset: &Typeset) -> Result<Document, Box<dyn Error>> {
+    let input_path = fileinfo.inputpath.clone();
+    let text = if epub::is_epub(&input_path) {
+        let book = epub::read_epub(&input_path)?;
+        if fileinfo.name.trim().is_empty() {
+            fileinfo.name = book.title;
+        }
+        if fileinfo.author.trim().is_empty() {
+            fileinfo.author = book.author;
+        }
+        book.text
+    } else {
+        get_txt(&input_path)?
+    };
+    // 解析为文档树：.vr 标记文本或纯文本
+    let mut doc = Document::parse(&text, &input_path);
+    // 简繁转换、阿拉伯数字转汉字，须在替换表之前，只转换文字不动标记
+    let script = Converter::new(typeset.script_convert.as_str(), typeset.convert_table_path.as_str());
+    doc.map_text(|text| {
+        let text = match &script {
+            Some(converter) => converter.convert(text),
+            None => text.to_string(),
+        };
+        numeral::convert_numbers(&text, typeset.number_mode.as_str())
+    });
+    Ok(doc)
+}
+
+/// 转义 XML 特殊字符，EPUB、SVG 导出共用
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
 
 /// 支持多种编码的文本读取（无 chardetrs，用 encoding_rs 试探）
 pub fn get_txt(file_name: &str) -> Result<String, Box<dyn Error>> {
@@ -55,75 +106,265 @@ fn filter_blank_lines(lines: &[String]) -> Vec<String> {
         .collect()
 }
 
-/// 处理文本并返回 [章][页][行] 三维数组
-pub fn process_text(text: &str, chars_per_line: usize, lines_per_page: usize) -> Vec<Vec<Vec<String>>> {
-    // 1. 按%%分割为章节
-    let chapters: Vec<&str> = text.split("%%").collect();
-    
-    // 2. 处理每个章节：分行 -> 分页
-    chapters.iter()
+/// 书名用作导出文件名：路径分隔符和 Windows 不允许的字符换为下划线，去掉首尾的空格和句点
+pub fn file_name_safe(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    let name = name.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    if name.is_empty() { "book".to_string() } else { name.to_string() }
+}
+
+/// 排版后的一行（竖排为一列）
+#[derive(Clone)]
+pub struct Line {
+    pub text: String,
+    pub indent: i32, // 低格数，负数为抬头
+    pub scale: f32,  // 字号与正文的比值，注文为小字
+}
+
+/// 按文档树分行分页，返回 [章][页][行] 三维数组
+/// latin_cells 给出旋转排列的拉丁文字占用的字位数
+pub fn layout_document(doc: &Document, chars_per_line: usize, lines_per_page: usize, typeset: &Typeset, substituter: &Converter, latin_cells: &dyn Fn(&str) -> usize) -> Vec<Vec<Vec<Line>>> {
+    doc.chapters
+        .iter()
         .map(|chapter| {
-            // 先将章节内容分割为行
-            let lines = split_into_lines(chapter, chars_per_line);
-            // 再将行分割为页
-            split_into_pages(&lines, lines_per_page)
+            // 按换页标记分段，每段单独分页
+            let mut segments: Vec<Vec<Line>> = vec![Vec::new()];
+            for block in &chapter.blocks {
+                let segment = segments.last_mut().unwrap();
+                match (block, block.text_and_indent()) {
+                    (_, Some((text, indent))) => {
+                        // 低格不超过列长，每列至少留一个字位；低格时每列可排字数相应减少，抬头时相应增加
+                        let indent = indent.clamp(-MAX_RAISE, (chars_per_line as i32 - 1).max(0));
+                        // 注文为小字，同样的列长可排更多字
+                        let scale = if matches!(block, Block::Note { .. }) { NOTE_SCALE } else { 1.0 };
+                        let width = (((chars_per_line as i32 - indent) as f32 / scale) as usize).max(1);
+                        segment.extend(
+                            split_into_lines(text, width, typeset, substituter, latin_cells)
+                                .into_iter()
+                                .map(|text| Line { text, indent, scale }),
+                        );
+                    }
+                    (Block::BlankLine, None) => segment.push(Line { text: String::new(), indent: 0, scale: 1.0 }),
+                    _ => segments.push(Vec::new()),
+                }
+            }
+            segments
+                .iter()
+                .flat_map(|segment| split_into_pages(segment, lines_per_page))
+                .collect()
         })
         .collect()
 }
-pub fn split_into_lines(text: &str, chars_per_line: usize) -> Vec<String> {
+pub fn split_into_lines(text: &str, chars_per_line: usize, typeset: &Typeset, substituter: &Converter, latin_cells: &dyn Fn(&str) -> usize) -> Vec<String> {
     // 存储所有行的集合
     let mut lines = Vec::new();
-    // 存储当前正在构建的行
-    let mut current_line = String::new();
     
-    let chars: Vec<char> = text.chars().collect();
+    // 白文不再有标点，句读符号不计入字符数
+    let chars = prepare_text(text, typeset, substituter);
 
-   // 记录当前行已添加的字符数
-    let mut char_count = 0;
+    // 逐段处理，段内按避头尾规则分行
+    for paragraph in chars.split(|c| *c == '\n') {
+        let units = split_into_units(paragraph, chars_per_line, latin_cells);
+        let mut start = 0;
+        while start < units.len() {
+            let end = find_line_end(&units, start, chars_per_line);
+            lines.push(units[start..end].iter().map(|unit| unit.text.as_str()).collect());
+            start = end;
+        }
+    }
+    // 过滤空白行
+    filter_blank_lines(&lines)
+}
+
+/// 先按替换表替换，再按标点处理方式转换或去除标点，PDF、EPUB 共用
+pub fn prepare_text(text: &str, typeset: &Typeset, substituter: &Converter) -> Vec<char> {
+    convert_punctuation_outside_ruby(&substituter.convert(text), typeset.punctuation_mode.as_str())
+}
+
+/// 标点处理不改动注音标记本身
+fn convert_punctuation_outside_ruby(text: &str, mode: &str) -> Vec<char> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = Vec::with_capacity(chars.len());
     let mut i = 0;
     while i < chars.len() {
-        let c = chars[i]; 
-            
-        // 处理换行符
-        if c == '\n' {
-            if !current_line.trim().is_empty() {
-                lines.push(current_line);
-            }
-            current_line = String::new();
-            char_count = 0;
-            i += 1;
+        if let Some((_, _, len)) = ruby::parse_ruby(&chars[i..]) {
+            result.extend_from_slice(&chars[i..i + len]);
+            i += len;
             continue;
         }
+        result.extend(convert_punctuation(chars[i], mode));
+        i += 1;
+    }
+    result
+}
 
-        // 添加当前字符到行
-        current_line.push(replace_char(c));        
-        // 非标点符号计入字符计数
-        if is_punctuation(c) != 1 {               
-            char_count += 1;
+/// 占位单元：一个字符、一段横排拉丁文字或一段注音正文，连同其后不占位的标点
+struct Unit {
+    text: String,
+    cells: usize, // 占用的字位数
+}
+
+impl Unit {
+    /// 避头尾按正文首字判断，跳过注音标记
+    fn first_char(&self) -> Option<char> {
+        match self.text.chars().next() {
+            Some(ruby::RUBY_OPEN) => ruby::strip_markup(&self.text).chars().next(),
+            first => first,
         }
+    }
+}
 
-        // 当字符数达到指定数量时检查是否需要包含下一个标点
-        if char_count == chars_per_line {
-            // 检查是否有下一个字符且为标点
-            if i + 1 < chars.len() && (is_punctuation(chars[i + 1]) == 1) {
-                // 加入下一个标点
-                let char_tmp = replace_char(chars[i + 1]);
-                current_line.push(char_tmp);                
-                i += 1; // 跳过已处理的标点
-            }            
-            // 完成当前行
-            lines.push(current_line);
-            current_line = String::new();
-            char_count = 0;
+/// 将段落拆分为占位单元：每个占位字符连同其后不占位的标点组成一个单元，拉丁文字整段为一个单元
+fn split_into_units(chars: &[char], chars_per_line: usize, latin_cells: &dyn Fn(&str) -> usize) -> Vec<Unit> {
+    let mut units: Vec<Unit> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some((base, gloss, len)) = ruby::parse_ruby(&chars[i..]) {
+            units.extend(split_ruby_span(&base, &gloss, chars_per_line));
+            i += len;
+            continue;
+        }
+        if is_latin(c) {
+            let end = latin_run_end(chars, i);
+            let run: String = chars[i..end].iter().collect();
+            units.extend(split_latin_run(&run, chars_per_line, latin_cells));
+            i = end;
+            continue;
+        }
+        match units.last_mut() {
+            Some(unit) if is_punctuation(c) == 1 => unit.text.push(c),
+            _ => units.push(Unit { text: c.to_string(), cells: 1 }),
         }
         i += 1;
     }
-    // 处理最后一行（如果存在未完成的行）
-    if !current_line.is_empty() {
-        lines.push(current_line);
+    units
+}
+
+/// 一行文字占用的字位数，不占位的标点不计
+pub fn line_cells(line: &str, latin_cells: &dyn Fn(&str) -> usize) -> usize {
+    let chars: Vec<char> = line.chars().collect();
+    split_into_units(&chars, usize::MAX, latin_cells).iter().map(|unit| unit.cells).sum()
+}
+
+/// 横排片段的排法
+#[derive(Clone, Copy, PartialEq)]
+pub enum LatinLayout {
+    Upright,     // 单个数字，与汉字一样直立
+    TateChuYoko, // 两三位数字，纵中横排在一个字位内
+    Rotated,     // 其余拉丁文字，顺时针旋转 90° 排列
+}
+
+/// 拉丁字母和数字，竖排时作为横排片段处理
+pub fn is_latin(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+}
+
+/// 从 start 开始的横排片段结束位置：连续的拉丁字母、数字，中间可夹空格、连字符、撇号和句点
+pub fn latin_run_end(chars: &[char], start: usize) -> usize {
+    let mut end = start;
+    while end < chars.len() {
+        let joined = end > start
+            && " -'.".contains(chars[end])
+            && chars.get(end + 1).is_some_and(|c| is_latin(*c));
+        if is_latin(chars[end]) || joined {
+            end += 1;
+        } else {
+            break;
+        }
     }
-    // 过滤空白行
-    filter_blank_lines(&lines)
+    end
+}
+
+pub fn latin_layout(run: &str) -> LatinLayout {
+    let digits = run.chars().all(|c| c.is_ascii_digit());
+    match run.chars().count() {
+        1 if digits => LatinLayout::Upright,
+        2 | 3 if digits => LatinLayout::TateChuYoko,
+        _ => LatinLayout::Rotated,
+    }
+}
+
+/// 横排片段占用的字位数：单个数字和纵中横占一个字位，旋转排列的按长度计
+fn run_cells(run: &str, latin_cells: &dyn Fn(&str) -> usize) -> usize {
+    match latin_layout(run) {
+        LatinLayout::Rotated => latin_cells(run),
+        _ => 1,
+    }
+}
+
+/// 超过一行的横排片段优先在词间拆开，没有合适的词间时在字母间拆开
+fn split_latin_run(run: &str, chars_per_line: usize, latin_cells: &dyn Fn(&str) -> usize) -> Vec<Unit> {
+    let mut units = Vec::new();
+    let mut rest = run;
+    while !rest.is_empty() {
+        let cells = run_cells(rest, latin_cells);
+        if cells <= chars_per_line {
+            units.push(Unit { text: rest.to_string(), cells });
+            break;
+        }
+        let boundaries: Vec<usize> = rest.char_indices().map(|(i, _)| i).skip(1).collect();
+        let fits = |i: usize| run_cells(&rest[..i], latin_cells) <= chars_per_line;
+        let cut = boundaries.iter().rev()
+            .filter(|&&i| rest[i..].starts_with(' '))
+            .find(|&&i| fits(i))
+            .or_else(|| boundaries.iter().rev().find(|&&i| fits(i)))
+            .or(boundaries.first())
+            .copied()
+            .unwrap_or(rest.len());
+        units.push(Unit { text: rest[..cut].to_string(), cells: run_cells(&rest[..cut], latin_cells) });
+        rest = rest[cut..].trim_start();
+    }
+    units
+}
+
+/// 注音正文整段不断开，超过一行时按行长拆开，注音随之拆分
+fn split_ruby_span(base: &str, gloss: &str, chars_per_line: usize) -> Vec<Unit> {
+    let base: Vec<char> = base.chars().collect();
+    let cells_of = |part: &[char]| part.iter().filter(|c| is_punctuation(**c) != 1).count().max(1);
+    let chunks: Vec<&[char]> = if cells_of(&base) <= chars_per_line {
+        vec![&base[..]]
+    } else {
+        base.chunks(chars_per_line.max(1)).collect()
+    };
+    let lengths: Vec<usize> = chunks.iter().map(|chunk| chunk.len()).collect();
+    chunks
+        .iter()
+        .zip(ruby::split_gloss(gloss, &lengths))
+        .map(|(chunk, gloss)| Unit {
+            text: ruby::markup(&chunk.iter().collect::<String>(), &gloss),
+            cells: cells_of(chunk),
+        })
+        .collect()
+}
+
+/// 从 start 开始按字位取满一行，按避头尾规则调整断行位置，返回下一行起始单元索引
+/// 行首遇到一个避头符号时悬挂或挤入本行，连续多个避头符号时将本行末字推到下一行
+fn find_line_end(units: &[Unit], start: usize, chars_per_line: usize) -> usize {
+    let mut end = start;
+    let mut cells = 0;
+    while end < units.len() && (end == start || cells + units[end].cells <= chars_per_line) {
+        cells += units[end].cells;
+        end += 1;
+    }
+    if end == units.len() {
+        return end;
+    }
+    let starts_forbidden = |i: usize| i < units.len() && units[i].first_char().is_some_and(is_line_start_forbidden);
+    let ends_forbidden = |i: usize| units[i].first_char().is_some_and(is_line_end_forbidden);
+
+    if starts_forbidden(end) && !starts_forbidden(end + 1) && !ends_forbidden(end - 1) {
+        // 只有一个避头符号，最多悬挂一个
+        return end + 1;
+    }
+    // 推出：回退断行位置，直到下一行不以避头符号开头、本行不以避尾符号结尾
+    while end > start + 1 && (starts_forbidden(end) || ends_forbidden(end - 1)) {
+        end -= 1;
+    }
+    end
 }
 
 /// 将行集合按指定行数分割成多页
@@ -131,7 +372,7 @@ pub fn split_into_lines(text: &str, chars_per_line: usize) -> Vec<String> {
 /// - lines: 行集合
 /// - lines_per_page: 每页包含的行数
 /// 返回: 分页后的页集合，每个元素是一页（包含多行）
-pub fn split_into_pages(lines: &[String], lines_per_page: usize) -> Vec<Vec<String>> {
+pub fn split_into_pages<T: Clone>(lines: &[T], lines_per_page: usize) -> Vec<Vec<T>> {
     // 存储所有页的集合
     let mut pages = Vec::new();
     // 存储当前正在构建的页，预分配容量提升性能
@@ -155,31 +396,45 @@ pub fn split_into_pages(lines: &[String], lines_per_page: usize) -> Vec<Vec<Stri
     pages
 }
 
-pub fn replace_char(c: char) -> char {
-    match c {
-        '1' => '一',
-        '2' => '二',
-        '3' => '三',
-        '4' => '四',
-        '5' => '五',
-        '6' => '六',
-        '7' => '七',
-        '8' => '八',
-        '9' => '九',
-        '0' => '〇',
-        '@' => ' ',
-        '\r' => ' ',
-        '\t' => ' ',
-        '“' => '『',
-        '”' => '』',
-        '‘' => '「',
-        '’' => '」',
-        _ => c,
+/// 按标点处理方式转换字符，返回 None 表示去除
+/// 保留标点：原样保留
+/// 白文：去除全部标点
+/// 句读：句末标点转为○，句中停顿转为、，其余标点去除
+pub fn convert_punctuation(c: char, mode: &str) -> Option<char> {
+    if c == '@' {
+        // 占位符不是标点，任何方式下都保留
+        return Some(c);
     }
+    let class = is_punctuation(c);
+    match mode {
+        "白文" => match class {
+            1 | 3 => None,
+            _ => Some(c),
+        },
+        "句读" => match c {
+            '。' | '！' | '？' | '；' | '○' => Some('○'),
+            '，' | '、' | '：' => Some('、'),
+            _ => match class {
+                1 | 3 => None,
+                _ => Some(c),
+            },
+        },
+        _ => Some(c),
+    }
+}
+
+/// 不能出现在行首的符号（避头）
+pub fn is_line_start_forbidden(c: char) -> bool {
+    "，。！？、；：○』」》）】>}…—".contains(c)
+}
+
+/// 不能出现在行尾的符号（避尾）
+pub fn is_line_end_forbidden(c: char) -> bool {
+    "『「《（【<{".contains(c)
 }
 
 pub fn is_punctuation(c: char) -> u8 {
-    let punctuation_chars = "，@。！？、；：";
+    let punctuation_chars = "，@。！？、；：○";
     let no_read_chars = "□〇1234567890abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
     let punctuation_chars_rotate = "{}（）……<>【】《》「」—『』-•——";
     if no_read_chars.contains(c) {
@@ -0,0 +1,141 @@
+use printpdf::{Color, Rgb, Line, LinePoint, Point, Pt};
+use crate::config::*;
+
+/// 刻本效果：按种子生成可复现的随机扰动
+/// 同一种子、同一页得到的结果完全一致，与多线程渲染顺序无关
+pub struct Jitter {
+    state: u64,
+    strength: f32,
+}
+
+/// 单个字符的扰动量
+pub struct GlyphJitter {
+    pub dx: Pt,          // x方向位移
+    pub dy: Pt,          // y方向位移
+    pub angle: f32,      // 旋转角度（度）
+    pub size_scale: f32, // 字体大小缩放
+    pub ink: f32,        // 墨色变淡比例（0为原色）
+}
+
+impl Jitter {
+    /// salt 用于区分页面，通常取 (章索引, 页索引) 组合
+    pub fn new(style: &Style, salt: u64) -> Self {
+        let strength = if style.woodblock { style.woodblock_strength.clamp(0.0, 1.0) } else { 0.0 };
+        let mut state = style.woodblock_seed ^ salt.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        if state == 0 {
+            state = 0x2545_F491_4F6C_DD1D;
+        }
+        Jitter { state, strength }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.strength > 0.0
+    }
+
+    // xorshift64*，返回 [0,1) 区间的随机数
+    fn next_unit(&mut self) -> f32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let v = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (v >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // 返回 [-1,1) 区间的随机数
+    fn next_signed(&mut self) -> f32 {
+        self.next_unit() * 2.0 - 1.0
+    }
+
+    /// 生成单个字符的位移、旋转、大小和墨色扰动
+    pub fn glyph(&mut self, fontsize: f32) -> GlyphJitter {
+        if !self.enabled() {
+            return GlyphJitter { dx: Pt(0.0), dy: Pt(0.0), angle: 0.0, size_scale: 1.0, ink: 0.0 };
+        }
+        let s = self.strength;
+        GlyphJitter {
+            dx: Pt(self.next_signed() * fontsize * JITTER_OFFSET_SCALE * s),
+            dy: Pt(self.next_signed() * fontsize * JITTER_OFFSET_SCALE * s),
+            angle: self.next_signed() * JITTER_ROTATE_DEG * s,
+            size_scale: 1.0 + self.next_signed() * JITTER_SIZE_SCALE * s,
+            ink: self.next_unit() * JITTER_INK * s,
+        }
+    }
+
+    /// 将折线转换为带波动和断口的线段集合
+    /// 未开启刻本效果时原样返回一条折线
+    pub fn rough_line(&mut self, points: &[Point], is_closed: bool) -> Vec<Line> {
+        if !self.enabled() || points.len() < 2 {
+            return vec![Line {
+                points: points.iter().map(|p| LinePoint { p: *p, bezier: false }).collect(),
+                is_closed,
+            }];
+        }
+        let mut corners = points.to_vec();
+        if is_closed {
+            corners.push(points[0]);
+        }
+        let wave = LINE_WAVE_PT * self.strength;
+        let break_rate = LINE_BREAK_RATE * self.strength;
+
+        let mut lines = Vec::new();
+        let mut current: Vec<LinePoint> = vec![LinePoint { p: corners[0], bezier: false }];
+        for pair in corners.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            let dx = end.x.0 - start.x.0;
+            let dy = end.y.0 - start.y.0;
+            let length = (dx * dx + dy * dy).sqrt();
+            if length <= 0.0 {
+                continue;
+            }
+            // 垂直于线段方向的单位向量
+            let (nx, ny) = (-dy / length, dx / length);
+            let steps = (length / LINE_WAVE_STEP_PT).ceil().max(1.0) as usize;
+            for step in 1..=steps {
+                let t = step as f32 / steps as f32;
+                // 拐角处不加波动，保证框线闭合
+                let offset = if step == steps { 0.0 } else { self.next_signed() * wave };
+                let p = Point {
+                    x: Pt(start.x.0 + dx * t + nx * offset),
+                    y: Pt(start.y.0 + dy * t + ny * offset),
+                };
+                if step < steps && self.next_unit() < break_rate {
+                    // 断口：结束当前线段，跳过一小段后重新起笔
+                    current.push(LinePoint { p, bezier: false });
+                    lines.push(Line { points: current, is_closed: false });
+                    // 断口长度小于一个采样间距，避免回笔
+                    let gap = (0.3 + self.next_unit() * 0.4) / steps as f32;
+                    let t_resume = t + gap;
+                    let resume = Point {
+                        x: Pt(start.x.0 + dx * t_resume),
+                        y: Pt(start.y.0 + dy * t_resume),
+                    };
+                    current = vec![LinePoint { p: resume, bezier: false }];
+                } else {
+                    current.push(LinePoint { p, bezier: false });
+                }
+            }
+        }
+        if current.len() > 1 {
+            lines.push(Line { points: current, is_closed: false });
+        }
+        lines
+    }
+}
+
+/// 按比例将颜色调淡，模拟着墨不匀
+pub fn fade_color(color: &Color, ink: f32) -> Color {
+    match color {
+        Color::Rgb(rgb) if ink > 0.0 => Color::Rgb(Rgb::new(
+            rgb.r + (1.0 - rgb.r) * ink,
+            rgb.g + (1.0 - rgb.g) * ink,
+            rgb.b + (1.0 - rgb.b) * ink,
+            None,
+        )),
+        _ => color.clone(),
+    }
+}
+
+/// 由章、页索引生成页面种子
+pub fn page_salt(chap_idx: usize, page_idx: usize) -> u64 {
+    ((chap_idx as u64) << 32) | (page_idx as u64 + 1)
+}